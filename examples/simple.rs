@@ -2,10 +2,8 @@ extern crate application;
 
 use application::*;
 
-fn main() {
+fn main() -> Result<(), AppError> {
     let config = AppConfig::new("Title Sample", (600, 400));
-    let mut app = App::new(config);
-    /*app.run(||{
-    
-    });*/
+    App::new(config)?.run(|_app, _frame| {});
+    Ok(())
 }
@@ -1,75 +1,1074 @@
 use events::*;
+use gamepad::*;
+use gestures::GestureRecognizer;
+#[cfg(feature = "gilrs")]
+use gilrs;
 use glutin;
-use AppConfig;
+use handler::*;
+use replay::*;
+use stats::FrameStats;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
 use std::os::raw::c_void;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::thread;
+use std::time::{Duration, Instant};
+use timing::FixedTimestep;
+use AppConfig;
+use AppError;
+use Frame;
 
-pub struct App {
+/// A window with its own GL context.
+struct NativeWindow {
+    id: WindowId,
     window: glutin::GlWindow,
-    events_loop: glutin::EventsLoop,
-    pub events: Vec<Event>,
 }
 
-impl App {
-    pub fn new(config: AppConfig) -> App {
-        use glutin::*;
-        let events_loop = glutin::EventsLoop::new();
-        let window = glutin::WindowBuilder::new()
-            .with_title(config.title)
-            .with_dimensions(config.size.0, config.size.1);
-        let context = glutin::ContextBuilder::new().with_vsync(config.vsync);
-        let gl_window = glutin::GlWindow::new(window, context, &events_loop).unwrap();
+pub struct App {
+    /// Open windows, the primary one first. Empty when replaying headless.
+    windows: Vec<NativeWindow>,
+    /// The window whose context is current.
+    current: WindowId,
+    next_window_id: u32,
+    vsync: bool,
+    events_loop: Option<glutin::EventsLoop>,
+    pub events: Vec<TimedEvent>,
+    pub input: InputState,
+    pub gamepads: Gamepads,
+    pub gestures: GestureRecognizer,
+    /// Events raised by `App` methods, delivered with the next frame.
+    queued: Vec<TimedEvent>,
+    text_input_enabled: bool,
+    /// When the app was created; the zero of `frame_time` and of event times.
+    start: Instant,
+    frame_time: f64,
+    frame: Option<Frame>,
+    /// Turns frame times into the fixed simulation steps of `Frame::steps`.
+    pub timestep: FixedTimestep,
+    /// Frame rate and frame time statistics.
+    pub stats: FrameStats,
+    recording: Option<Recording>,
+    record_path: Option<PathBuf>,
+    player: Option<Player>,
+    /// Replay at the recorded pace rather than as fast as possible.
+    realtime: bool,
+}
 
-        unsafe {
-            gl_window.make_current().unwrap();
+impl App {
+    pub fn new(config: AppConfig) -> Result<App, AppError> {
+        config.validate()?;
+        let (mut recording, mut record_path, mut player) = (None, None, None);
+        let (mut realtime, mut headless) = (false, false);
+        match config.run_mode {
+            RunMode::Live => (),
+            RunMode::Record { path } => {
+                recording = Some(Recording::new());
+                record_path = path;
+            }
+            RunMode::Replay {
+                recording,
+                realtime: replay_realtime,
+                headless: replay_headless,
+            } => {
+                player = Some(Player::new(recording));
+                realtime = replay_realtime;
+                headless = replay_headless;
+            }
         }
-        App {
-            window: gl_window,
+
+        let events_loop = if headless {
+            None
+        } else {
+            Some(glutin::EventsLoop::new())
+        };
+        let mut app = App {
+            windows: Vec::new(),
+            current: WindowId::PRIMARY,
+            next_window_id: WindowId::PRIMARY.0,
+            vsync: config.vsync,
             events_loop,
             events: Vec::new(),
+            input: InputState::new(),
+            gamepads: Gamepads::new(default_gamepad_source()),
+            gestures: GestureRecognizer::default(),
+            queued: Vec::new(),
+            text_input_enabled: false,
+            start: Instant::now(),
+            frame_time: 0.0,
+            frame: None,
+            timestep: FixedTimestep::new(config.fixed_rate),
+            stats: FrameStats::default(),
+            recording,
+            record_path,
+            player,
+            realtime,
+        };
+        if !headless {
+            app.create_window(&config.title, config.size)?;
         }
+        Ok(app)
     }
 
+    /// Opens another window with its own GL context and returns its id.
+    ///
+    /// Events of the window carry the id. The current window stays current; switch with
+    /// `set_current_window` to draw into the new one.
+    pub fn create_window(&mut self, title: &str, size: (u32, u32)) -> Result<WindowId, AppError> {
+        use glutin::GlContext;
+        let events_loop = match self.events_loop {
+            Some(ref events_loop) => events_loop,
+            None => return Err(AppError("no windows while replaying headless".to_string())),
+        };
+        let window = glutin::WindowBuilder::new()
+            .with_title(title)
+            .with_dimensions(glutin::dpi::LogicalSize::new(size.0 as f64, size.1 as f64));
+        let context = glutin::ContextBuilder::new().with_vsync(self.vsync);
+        let window = glutin::GlWindow::new(window, context, events_loop)
+            .map_err(|err| AppError(err.to_string()))?;
+
+        let id = WindowId(self.next_window_id);
+        self.next_window_id += 1;
+        if self.windows.is_empty() {
+            unsafe {
+                window
+                    .make_current()
+                    .map_err(|err| AppError(err.to_string()))?;
+            }
+            self.current = id;
+        }
+        self.windows.push(NativeWindow { id, window });
+        Ok(id)
+    }
+
+    /// Closes a window. If it was the current one, the first remaining window becomes current.
+    ///
+    /// `run` returns once every window is closed.
+    pub fn close_window(&mut self, id: WindowId) {
+        self.windows.retain(|window| window.id != id);
+        if self.current == id {
+            if let Some(first) = self.windows.first().map(|window| window.id) {
+                self.set_current_window(first);
+            }
+        }
+    }
+
+    /// Ids of the open windows, the primary one first.
+    pub fn windows(&self) -> Vec<WindowId> {
+        self.windows.iter().map(|window| window.id).collect()
+    }
+
+    /// The window that drawing and the window methods of `App` apply to.
+    pub fn current_window(&self) -> WindowId {
+        self.current
+    }
+
+    /// Makes a window's GL context current. Returns false if there is no such window.
+    pub fn set_current_window(&mut self, id: WindowId) -> bool {
+        use glutin::GlContext;
+        let window = match self.windows.iter().find(|window| window.id == id) {
+            Some(window) => window,
+            None => return false,
+        };
+        if let Err(err) = unsafe { window.window.make_current() } {
+            log(&format!("could not make window {} current: {}", id.0, err));
+            return false;
+        }
+        self.current = id;
+        true
+    }
+
+    fn current(&self) -> Option<&glutin::GlWindow> {
+        self.windows
+            .iter()
+            .find(|window| window.id == self.current)
+            .map(|window| &window.window)
+    }
+
+    /// The current window.
+    ///
+    /// Panics when replaying headless.
     pub fn window(&self) -> &glutin::GlWindow {
-        &self.window
+        self.current().expect("no window while replaying headless")
     }
 
+    /// Looks up a GL function for the current window's context. Returns null for every name
+    /// when replaying headless.
     pub fn get_proc_address(&self, name: &str) -> *const c_void {
         use glutin::GlContext;
-        self.window().get_proc_address(name) as *const c_void
+        match self.current() {
+            Some(window) => window.get_proc_address(name) as *const c_void,
+            None => ptr::null(),
+        }
+    }
+
+    /// Size of the current window's drawable area, in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        self.current()
+            .and_then(|window| {
+                let size = window.get_inner_size()?;
+                Some(size.to_physical(window.get_hidpi_factor()).into())
+            })
+            .unwrap_or((0, 0))
+    }
+
+    /// Ratio of the current window's pixels to logical pixels.
+    pub fn hidpi_factor(&self) -> f64 {
+        self.current()
+            .map_or(1.0, |window| window.get_hidpi_factor())
+    }
+
+    /// Converts a position in window pixels, like `CursorMoved.position`, to logical pixels.
+    ///
+    /// Uses the HiDPI factor of the current window. With several windows, switch to the event's
+    /// `window_id` with `set_current_window` first.
+    pub fn to_logical(&self, position: (f64, f64)) -> (f64, f64) {
+        let factor = self.hidpi_factor();
+        (position.0 / factor, position.1 / factor)
+    }
+
+    /// Time at the start of the current frame, in milliseconds since the app was created.
+    ///
+    /// When replaying this is the recorded time, counted from the first recorded frame.
+    pub fn frame_time(&self) -> f64 {
+        self.frame_time
+    }
+
+    /// The frames recorded so far, in `RunMode::Record`.
+    pub fn recording(&self) -> Option<&Recording> {
+        self.recording.as_ref()
+    }
+
+    /// Grabs or releases the cursor for the current window.
+    ///
+    /// While grabbed the cursor is hidden and confined to the window, and mouse movement keeps
+    /// arriving as `DeviceEvent::MouseMotion`. The outcome is reported with `CursorGrabChanged`
+    /// or `CursorGrabFailed`.
+    pub fn set_cursor_grab(&mut self, grab: bool) {
+        let result = match self.current() {
+            Some(window) => window.grab_cursor(grab).map(|()| window.hide_cursor(grab)),
+            None => Err("no window".to_string()),
+        };
+        let event = match result {
+            Ok(()) => WindowEvent::CursorGrabChanged(grab),
+            Err(err) => {
+                log(&format!("could not grab the cursor: {}", err));
+                WindowEvent::CursorGrabFailed
+            }
+        };
+        self.queue_window_event(event);
+    }
+
+    /// Starts or stops text input.
+    ///
+    /// Native windows always deliver typed text as `ReceivedCharacter` events; this only
+    /// reports `Ime::Enabled` and `Ime::Disabled` so apps behave the same as on the web.
+    pub fn set_text_input_enabled(&mut self, enabled: bool) {
+        if self.text_input_enabled == enabled {
+            return;
+        }
+        self.text_input_enabled = enabled;
+        let ime = if enabled { Ime::Enabled } else { Ime::Disabled };
+        self.queue_window_event(WindowEvent::Ime(ime));
+    }
+
+    fn queue_window_event(&mut self, event: WindowEvent) {
+        let time = millis(self.start.elapsed());
+        self.queued.push(TimedEvent::new(
+            time,
+            Event::WindowEvent {
+                window_id: self.current,
+                event,
+            },
+        ));
+    }
+
+    /// Moves the input method candidate window of the current window next to the given position,
+    /// in window pixels. Does nothing when replaying headless.
+    pub fn set_ime_position(&mut self, position: (f64, f64)) {
+        if let Some(window) = self.current() {
+            let position = glutin::dpi::PhysicalPosition::new(position.0, position.1);
+            window.set_ime_spot(glutin::dpi::LogicalPosition::from_physical(
+                position,
+                window.get_hidpi_factor(),
+            ));
+        }
     }
 
     pub fn canvas(&self) -> &isize {
         &0
     }
-    
-    pub fn run<F>(mut self, mut callback: F) where F: 'static+FnMut(&mut App) -> () 
+
+    /// Calls `callback` once per frame until every window is closed or the replay ends. Blocks
+    /// until then.
+    ///
+    /// A window closed by the user is closed after the frame that reports its `Closed` event.
+    ///
+    /// When replaying, `events` holds the recorded events instead of live input. Events raised
+    /// by `App` methods are dropped, since the recording already contains them.
+    pub fn run<F>(mut self, mut callback: F)
+    where
+        F: 'static + FnMut(&mut App, &Frame),
     {
-        use glutin::*;
-        let mut running = true;
-        while running {
+        self.run_frames(&mut callback);
+    }
+
+    /// Runs `handler` like `run` runs a callback, then calls its `exit` hook.
+    pub fn run_handler<H>(mut self, handler: H)
+    where
+        H: 'static + ApplicationHandler,
+    {
+        let mut driver = HandlerDriver::new(handler);
+        self.run_frames(&mut |app: &mut App, frame: &Frame| driver.frame(app, frame));
+        driver.exit(&mut self);
+    }
+
+    fn run_frames<F>(&mut self, callback: &mut F)
+    where
+        F: FnMut(&mut App, &Frame),
+    {
+        let live = self.player.is_none();
+        let replay_start = Instant::now();
+        let mut polled = Vec::new();
+        let mut closed = Vec::new();
+        loop {
             self.events.clear();
-            {
-                let (window, events_loop, events) =
-                    (&self.window, &mut self.events_loop, &mut self.events);
+            if live {
+                self.events.append(&mut self.queued);
+            } else {
+                self.queued.clear();
+            }
+            if let Some(ref mut events_loop) = self.events_loop {
+                let (windows, events, start) = (&self.windows, &mut self.events, self.start);
                 events_loop.poll_events(|event| {
-                    match event {
-                        glutin::Event::WindowEvent { ref event, .. } => match event {
-                            &glutin::WindowEvent::Closed => running = false,
-                            &glutin::WindowEvent::Resized(w, h) => window.resize(w, h),
-                            _ => (),
-                        },
-                        _ => (),
+                    let window = match event {
+                        glutin::Event::WindowEvent { window_id, .. } => windows
+                            .iter()
+                            .find(|window| window.window.id() == window_id),
+                        _ => None,
                     };
-                    events.push(event);
+                    if let (Some(window), glutin::Event::WindowEvent { event, .. }) =
+                        (window, &event)
+                    {
+                        match *event {
+                            glutin::WindowEvent::CloseRequested => closed.push(window.id),
+                            glutin::WindowEvent::Resized(size) => {
+                                let factor = window.window.get_hidpi_factor();
+                                window.window.resize(size.to_physical(factor));
+                            }
+                            _ => (),
+                        }
+                    }
+                    if live {
+                        let window =
+                            window.map(|window| (window.id, window.window.get_hidpi_factor()));
+                        push_glutin_event(events, millis(start.elapsed()), window, &event);
+                    }
                 });
             }
 
-            callback(&mut self);
-            self.window.swap_buffers().unwrap();
+            if live {
+                self.frame_time = millis(self.start.elapsed());
+                self.gamepads.poll(&mut polled);
+                let time = self.frame_time;
+                self.events
+                    .extend(polled.drain(..).map(|event| TimedEvent::new(time, event)));
+            } else if !self.next_replay_frame(replay_start) {
+                break;
+            }
+            if let Some(ref mut recording) = self.recording {
+                recording.push(self.frame_time, &self.events);
+            }
+            self.input.update(&self.events);
+            self.gestures.update(self.frame_time, &self.events);
+            let frame = Frame::after(self.frame, self.frame_time, &mut self.timestep);
+            self.frame = Some(frame);
+            self.stats.frame_started(&frame);
+            let callback_start = Instant::now();
+            callback(self, &frame);
+            let callback_time = millis(callback_start.elapsed());
+            self.stats.callback_finished(&frame, callback_time);
+            self.swap_buffers();
+
+            for id in closed.drain(..) {
+                self.close_window(id);
+            }
+            if self.events_loop.is_some() && self.windows.is_empty() {
+                break;
+            }
+        }
+        self.save_recording();
+    }
+
+    /// Presents every window. A context has to be current on the thread to swap its buffers, so
+    /// each window is made current in turn and the current window is restored afterwards.
+    fn swap_buffers(&self) {
+        use glutin::GlContext;
+        for window in &self.windows {
+            if window.id != self.current {
+                if let Err(err) = unsafe { window.window.make_current() } {
+                    log(&format!(
+                        "could not make window {} current: {}",
+                        window.id.0, err
+                    ));
+                    continue;
+                }
+            }
+            if let Err(err) = window.window.swap_buffers() {
+                log(&format!(
+                    "could not swap buffers of window {}: {}",
+                    window.id.0, err
+                ));
+            }
+        }
+        if self.windows.len() > 1 {
+            if let Some(current) = self.current() {
+                if let Err(err) = unsafe { current.make_current() } {
+                    log(&format!(
+                        "could not make window {} current: {}",
+                        self.current.0, err
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Loads the next recorded frame into `events`, waiting for its time when replaying in
+    /// real time. Returns false at the end of the recording.
+    fn next_replay_frame(&mut self, replay_start: Instant) -> bool {
+        let player = match self.player {
+            Some(ref mut player) => player,
+            None => return false,
+        };
+        let frame = match player.next_frame() {
+            Some(frame) => frame,
+            None => return false,
+        };
+        if self.realtime {
+            let due = replay_start + Duration::from_micros((frame.time.max(0.0) * 1000.0) as u64);
+            let now = Instant::now();
+            if due > now {
+                thread::sleep(due - now);
+            }
+        }
+        self.frame_time = frame.time;
+        self.events = frame.events;
+        true
+    }
+
+    #[cfg(feature = "json")]
+    fn save_recording(&self) {
+        if let (Some(recording), Some(path)) = (self.recording.as_ref(), self.record_path.as_ref())
+        {
+            if let Err(err) = recording.save(path) {
+                log(&format!(
+                    "could not save recording to {}: {}",
+                    path.display(),
+                    err
+                ));
+            }
         }
     }
+
+    #[cfg(not(feature = "json"))]
+    fn save_recording(&self) {
+        if let Some(ref path) = self.record_path {
+            log(&format!(
+                "recording not saved to {}: built without the json feature",
+                path.display()
+            ));
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_nanos()) / 1_000_000.0
 }
 
-pub fn log(msg:&str){
-    println!("LOG: {}",msg);
-}
\ No newline at end of file
+pub fn log(msg: &str) {
+    println!("LOG: {}", msg);
+}
+
+#[cfg(feature = "gilrs")]
+fn default_gamepad_source() -> Box<dyn GamepadSource> {
+    Box::new(GilrsGamepads::new())
+}
+
+#[cfg(not(feature = "gilrs"))]
+fn default_gamepad_source() -> Box<dyn GamepadSource> {
+    Box::new(NoGamepads)
+}
+
+/// Reads gamepads through gilrs.
+#[cfg(feature = "gilrs")]
+pub struct GilrsGamepads {
+    gilrs: Option<gilrs::Gilrs>,
+}
+
+#[cfg(feature = "gilrs")]
+impl GilrsGamepads {
+    /// Creates the source. If gilrs cannot be initialized no gamepads are ever reported.
+    pub fn new() -> GilrsGamepads {
+        GilrsGamepads {
+            gilrs: gilrs::Gilrs::new().ok(),
+        }
+    }
+}
+
+#[cfg(feature = "gilrs")]
+impl Default for GilrsGamepads {
+    fn default() -> GilrsGamepads {
+        GilrsGamepads::new()
+    }
+}
+
+#[cfg(feature = "gilrs")]
+impl GamepadSource for GilrsGamepads {
+    fn poll(&mut self, pads: &mut Vec<Option<GamepadState>>) {
+        use gilrs::{Axis, Button};
+
+        pads.clear();
+        let gilrs = match self.gilrs {
+            Some(ref mut gilrs) => gilrs,
+            None => return,
+        };
+        // gilrs only updates its gamepad state while its events are drained.
+        while gilrs.next_event().is_some() {}
+
+        for (id, pad) in gilrs.gamepads() {
+            let index: usize = id.into();
+            if pads.len() <= index {
+                pads.resize(index + 1, None);
+            }
+
+            let mut state = GamepadState::new(pad.name());
+            for &button in GamepadButton::ALL.iter() {
+                let native = match button {
+                    GamepadButton::South => Button::South,
+                    GamepadButton::East => Button::East,
+                    GamepadButton::West => Button::West,
+                    GamepadButton::North => Button::North,
+                    GamepadButton::LeftBumper => Button::LeftTrigger,
+                    GamepadButton::RightBumper => Button::RightTrigger,
+                    GamepadButton::LeftTrigger => Button::LeftTrigger2,
+                    GamepadButton::RightTrigger => Button::RightTrigger2,
+                    GamepadButton::Select => Button::Select,
+                    GamepadButton::Start => Button::Start,
+                    GamepadButton::LeftStick => Button::LeftThumb,
+                    GamepadButton::RightStick => Button::RightThumb,
+                    GamepadButton::DPadUp => Button::DPadUp,
+                    GamepadButton::DPadDown => Button::DPadDown,
+                    GamepadButton::DPadLeft => Button::DPadLeft,
+                    GamepadButton::DPadRight => Button::DPadRight,
+                    GamepadButton::Mode => Button::Mode,
+                };
+                state.buttons[button as usize] = pad.is_pressed(native);
+            }
+
+            // gilrs reports up as positive, the standard layout as negative.
+            state.axes[GamepadAxis::LeftStickX as usize] = pad.value(Axis::LeftStickX) as f64;
+            state.axes[GamepadAxis::LeftStickY as usize] = -pad.value(Axis::LeftStickY) as f64;
+            state.axes[GamepadAxis::RightStickX as usize] = pad.value(Axis::RightStickX) as f64;
+            state.axes[GamepadAxis::RightStickY as usize] = -pad.value(Axis::RightStickY) as f64;
+            let trigger = |button| {
+                pad.button_data(button)
+                    .map_or(0.0, |data| data.value() as f64)
+            };
+            state.axes[GamepadAxis::LeftTrigger as usize] = trigger(Button::LeftTrigger2);
+            state.axes[GamepadAxis::RightTrigger as usize] = trigger(Button::RightTrigger2);
+
+            pads[index] = Some(state);
+        }
+    }
+}
+
+/// Translates and queues a glutin event. `window` is the id and hidpi factor of the window the
+/// event is for, if it is a window event of an open window.
+fn push_glutin_event(
+    events: &mut Vec<TimedEvent>,
+    time: f64,
+    window: Option<(WindowId, f64)>,
+    event: &glutin::Event,
+) {
+    let event = match Event::from_glutin_event(event, window) {
+        Some(event) => event,
+        None => return,
+    };
+    let contents = match event {
+        Event::WindowEvent {
+            window_id,
+            event: WindowEvent::DroppedFile(ref path),
+        } => {
+            let event = match FileContents::read(path) {
+                Ok(contents) => WindowEvent::DroppedFileContents(contents),
+                Err(err) => {
+                    log(&format!(
+                        "could not read dropped file {}: {}",
+                        path.display(),
+                        err
+                    ));
+                    WindowEvent::DroppedFileFailed {
+                        name: file_name(path),
+                        error: err.to_string(),
+                    }
+                }
+            };
+            Some(Event::WindowEvent { window_id, event })
+        }
+        _ => None,
+    };
+    events.push(TimedEvent::new(time, event));
+    if let Some(contents) = contents {
+        events.push(TimedEvent::new(time, contents));
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+impl FileContents {
+    /// Reads a dropped file, guessing its MIME type from the extension.
+    fn read(path: &Path) -> io::Result<FileContents> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        let mime = match extension.as_deref() {
+            Some("png") => "image/png",
+            Some("jpg") | Some("jpeg") => "image/jpeg",
+            Some("gif") => "image/gif",
+            Some("webp") => "image/webp",
+            Some("svg") => "image/svg+xml",
+            Some("bmp") => "image/bmp",
+            Some("txt") => "text/plain",
+            Some("json") => "application/json",
+            Some("xml") => "application/xml",
+            Some("html") | Some("htm") => "text/html",
+            Some("css") => "text/css",
+            Some("js") => "text/javascript",
+            Some("csv") => "text/csv",
+            Some("wav") => "audio/wav",
+            Some("mp3") => "audio/mpeg",
+            Some("ogg") => "audio/ogg",
+            Some("mp4") => "video/mp4",
+            Some("webm") => "video/webm",
+            Some("ttf") => "font/ttf",
+            Some("otf") => "font/otf",
+            Some("woff") => "font/woff",
+            Some("woff2") => "font/woff2",
+            Some("zip") => "application/zip",
+            Some("pdf") => "application/pdf",
+            Some("wasm") => "application/wasm",
+            Some("gltf") => "model/gltf+json",
+            Some("glb") => "model/gltf-binary",
+            _ => "",
+        };
+        Ok(FileContents {
+            name: file_name(path),
+            mime: mime.to_string(),
+            data: fs::read(path)?,
+        })
+    }
+}
+
+impl Event {
+    /// Translates a glutin event into the crate's own `Event` type. Window events need the id
+    /// and hidpi factor of their window.
+    ///
+    /// Returns `None` for window events of windows that are already closed.
+    fn from_glutin_event(event: &glutin::Event, window: Option<(WindowId, f64)>) -> Option<Event> {
+        Some(match *event {
+            glutin::Event::WindowEvent { ref event, .. } => {
+                let (window_id, factor) = window?;
+                Event::WindowEvent {
+                    window_id,
+                    event: WindowEvent::from_glutin(event, factor),
+                }
+            }
+            glutin::Event::DeviceEvent {
+                device_id,
+                ref event,
+            } => Event::DeviceEvent {
+                device_id: from_glutin_device(DeviceEvent::device_kind(event), &device_id),
+                event: event.into(),
+            },
+            glutin::Event::Awakened => Event::Awakened,
+            glutin::Event::Suspended(suspended) => Event::Suspended(suspended),
+        })
+    }
+}
+
+impl WindowEvent {
+    /// glutin reports sizes and positions in logical pixels; ours are in window pixels, so they
+    /// are scaled by the window's hidpi `factor`.
+    fn from_glutin(event: &glutin::WindowEvent, factor: f64) -> WindowEvent {
+        use glutin::WindowEvent as W;
+        match *event {
+            W::Resized(size) => {
+                let (width, height) = size.to_physical(factor).into();
+                WindowEvent::Resized(width, height)
+            }
+            W::Moved(position) => {
+                let (x, y) = position.to_physical(factor).into();
+                WindowEvent::Moved(x, y)
+            }
+            W::CloseRequested | W::Destroyed => WindowEvent::Closed,
+            W::Focused(focused) => WindowEvent::Focused(focused),
+            W::ReceivedCharacter(c) => WindowEvent::ReceivedCharacter(c),
+            W::DroppedFile(ref path) => WindowEvent::DroppedFile(path.clone()),
+            W::HoveredFile(ref path) => WindowEvent::HoveredFile(path.clone()),
+            W::HoveredFileCancelled => WindowEvent::HoveredFileCancelled,
+            W::HiDpiFactorChanged(factor) => WindowEvent::HiDPIFactorChanged(factor as f32),
+            W::Refresh => WindowEvent::Refresh,
+            W::KeyboardInput { device_id, input } => WindowEvent::KeyboardInput {
+                device_id: from_glutin_device(DeviceKind::Keyboard, &device_id),
+                input: input.into(),
+            },
+            W::CursorMoved {
+                device_id,
+                position,
+                modifiers,
+            } => WindowEvent::CursorMoved {
+                device_id: from_glutin_device(DeviceKind::Mouse, &device_id),
+                position: position.to_physical(factor).into(),
+                modifiers: modifiers.into(),
+                pointer: None,
+            },
+            W::CursorEntered { device_id } => WindowEvent::CursorEntered {
+                device_id: from_glutin_device(DeviceKind::Mouse, &device_id),
+            },
+            W::CursorLeft { device_id } => WindowEvent::CursorLeft {
+                device_id: from_glutin_device(DeviceKind::Mouse, &device_id),
+            },
+            W::MouseWheel {
+                device_id,
+                delta,
+                phase,
+                modifiers,
+            } => WindowEvent::MouseWheel {
+                device_id: from_glutin_device(DeviceKind::Mouse, &device_id),
+                delta: delta.into(),
+                phase: phase.into(),
+                modifiers: modifiers.into(),
+            },
+            W::MouseInput {
+                device_id,
+                state,
+                button,
+                modifiers,
+            } => WindowEvent::MouseInput {
+                device_id: from_glutin_device(DeviceKind::Mouse, &device_id),
+                state: state.into(),
+                button: button.into(),
+                modifiers: modifiers.into(),
+            },
+            W::TouchpadPressure {
+                device_id,
+                pressure,
+                stage,
+            } => WindowEvent::TouchpadPressure {
+                device_id: from_glutin_device(DeviceKind::Mouse, &device_id),
+                pressure,
+                stage,
+            },
+            W::AxisMotion {
+                device_id,
+                axis,
+                value,
+            } => WindowEvent::AxisMotion {
+                device_id: from_glutin_device(DeviceKind::Other, &device_id),
+                axis,
+                value,
+            },
+            W::Touch(touch) => WindowEvent::Touch(Touch::from_glutin(touch, factor)),
+        }
+    }
+}
+
+impl DeviceEvent {
+    /// glutin does not say what kind of device sent an event, so it is told by the event.
+    fn device_kind(event: &glutin::DeviceEvent) -> DeviceKind {
+        use glutin::DeviceEvent as D;
+        match *event {
+            D::MouseMotion { .. } | D::MouseWheel { .. } => DeviceKind::Mouse,
+            D::Key(_) | D::Text { .. } => DeviceKind::Keyboard,
+            D::Added | D::Removed | D::Motion { .. } | D::Button { .. } => DeviceKind::Other,
+        }
+    }
+}
+
+impl From<&glutin::DeviceEvent> for DeviceEvent {
+    fn from(event: &glutin::DeviceEvent) -> DeviceEvent {
+        use glutin::DeviceEvent as D;
+        match *event {
+            D::Added => DeviceEvent::Added,
+            D::Removed => DeviceEvent::Removed,
+            D::MouseMotion { delta } => DeviceEvent::MouseMotion { delta },
+            D::MouseWheel { delta } => DeviceEvent::MouseWheel {
+                delta: delta.into(),
+            },
+            D::Motion { axis, value } => DeviceEvent::Motion { axis, value },
+            D::Button { button, state } => DeviceEvent::Button {
+                button,
+                state: state.into(),
+            },
+            D::Key(input) => DeviceEvent::Key(input.into()),
+            D::Text { codepoint } => DeviceEvent::Text { codepoint },
+        }
+    }
+}
+
+impl Touch {
+    fn from_glutin(touch: glutin::Touch, factor: f64) -> Touch {
+        Touch {
+            device_id: from_glutin_device(DeviceKind::Touch, &touch.device_id),
+            phase: touch.phase.into(),
+            location: touch.location.to_physical(factor).into(),
+            id: touch.id,
+            pointer: None,
+        }
+    }
+}
+
+/// glutin device ids are opaque, so ours are made from their hash. The kind is told by the
+/// event, so compare `DeviceId::id` to match up the events of one device.
+fn from_glutin_device(kind: DeviceKind, device_id: &glutin::DeviceId) -> DeviceId {
+    let mut hasher = DefaultHasher::new();
+    device_id.hash(&mut hasher);
+    DeviceId::new(kind, hasher.finish())
+}
+
+impl From<glutin::KeyboardInput> for KeyboardInput {
+    fn from(input: glutin::KeyboardInput) -> KeyboardInput {
+        KeyboardInput {
+            scancode: input.scancode,
+            state: input.state.into(),
+            virtual_keycode: input.virtual_keycode.and_then(VirtualKeyCode::from_glutin),
+            modifiers: input.modifiers.into(),
+        }
+    }
+}
+
+impl From<glutin::ModifiersState> for ModifiersState {
+    fn from(modifiers: glutin::ModifiersState) -> ModifiersState {
+        ModifiersState {
+            shift: modifiers.shift,
+            ctrl: modifiers.ctrl,
+            alt: modifiers.alt,
+            logo: modifiers.logo,
+        }
+    }
+}
+
+impl From<glutin::ElementState> for ElementState {
+    fn from(state: glutin::ElementState) -> ElementState {
+        match state {
+            glutin::ElementState::Pressed => ElementState::Pressed,
+            glutin::ElementState::Released => ElementState::Released,
+        }
+    }
+}
+
+impl From<glutin::MouseButton> for MouseButton {
+    fn from(button: glutin::MouseButton) -> MouseButton {
+        match button {
+            glutin::MouseButton::Left => MouseButton::Left,
+            glutin::MouseButton::Right => MouseButton::Right,
+            glutin::MouseButton::Middle => MouseButton::Middle,
+            glutin::MouseButton::Other(n) => MouseButton::Other(n),
+        }
+    }
+}
+
+impl From<glutin::MouseScrollDelta> for MouseScrollDelta {
+    fn from(delta: glutin::MouseScrollDelta) -> MouseScrollDelta {
+        match delta {
+            glutin::MouseScrollDelta::LineDelta(x, y) => MouseScrollDelta::LineDelta(x, y),
+            glutin::MouseScrollDelta::PixelDelta(delta) => {
+                MouseScrollDelta::PixelDelta(delta.x as f32, delta.y as f32)
+            }
+        }
+    }
+}
+
+impl From<glutin::TouchPhase> for TouchPhase {
+    fn from(phase: glutin::TouchPhase) -> TouchPhase {
+        match phase {
+            glutin::TouchPhase::Started => TouchPhase::Started,
+            glutin::TouchPhase::Moved => TouchPhase::Moved,
+            glutin::TouchPhase::Ended => TouchPhase::Ended,
+            glutin::TouchPhase::Cancelled => TouchPhase::Cancelled,
+        }
+    }
+}
+
+// Our `VirtualKeyCode` shares most variants with glutin's. The keys glutin has and we don't
+// (F16-F24, Caret, Copy, Cut and Paste) are left unidentified.
+macro_rules! convert_keycodes {
+    ($($name:ident,)*) => {
+        impl VirtualKeyCode {
+            fn from_glutin(key: glutin::VirtualKeyCode) -> Option<VirtualKeyCode> {
+                match key {
+                    $(glutin::VirtualKeyCode::$name => Some(VirtualKeyCode::$name),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+convert_keycodes! {
+            Key1,
+            Key2,
+            Key3,
+            Key4,
+            Key5,
+            Key6,
+            Key7,
+            Key8,
+            Key9,
+            Key0,
+            A,
+            B,
+            C,
+            D,
+            E,
+            F,
+            G,
+            H,
+            I,
+            J,
+            K,
+            L,
+            M,
+            N,
+            O,
+            P,
+            Q,
+            R,
+            S,
+            T,
+            U,
+            V,
+            W,
+            X,
+            Y,
+            Z,
+            Escape,
+            F1,
+            F2,
+            F3,
+            F4,
+            F5,
+            F6,
+            F7,
+            F8,
+            F9,
+            F10,
+            F11,
+            F12,
+            F13,
+            F14,
+            F15,
+            Snapshot,
+            Scroll,
+            Pause,
+            Insert,
+            Home,
+            Delete,
+            End,
+            PageDown,
+            PageUp,
+            Left,
+            Up,
+            Right,
+            Down,
+            Back,
+            Return,
+            Space,
+            Compose,
+            Numlock,
+            Numpad0,
+            Numpad1,
+            Numpad2,
+            Numpad3,
+            Numpad4,
+            Numpad5,
+            Numpad6,
+            Numpad7,
+            Numpad8,
+            Numpad9,
+            AbntC1,
+            AbntC2,
+            Add,
+            Apostrophe,
+            Apps,
+            At,
+            Ax,
+            Backslash,
+            Calculator,
+            Capital,
+            Colon,
+            Comma,
+            Convert,
+            Decimal,
+            Divide,
+            Equals,
+            Grave,
+            Kana,
+            Kanji,
+            LAlt,
+            LBracket,
+            LControl,
+            LShift,
+            LWin,
+            Mail,
+            MediaSelect,
+            MediaStop,
+            Minus,
+            Multiply,
+            Mute,
+            MyComputer,
+            NavigateForward,
+            NavigateBackward,
+            NextTrack,
+            NoConvert,
+            NumpadComma,
+            NumpadEnter,
+            NumpadEquals,
+            OEM102,
+            Period,
+            PlayPause,
+            Power,
+            PrevTrack,
+            RAlt,
+            RBracket,
+            RControl,
+            RShift,
+            RWin,
+            Semicolon,
+            Slash,
+            Sleep,
+            Stop,
+            Subtract,
+            Sysrq,
+            Tab,
+            Underline,
+            Unlabeled,
+            VolumeDown,
+            VolumeUp,
+            Wake,
+            WebBack,
+            WebFavorites,
+            WebForward,
+            WebHome,
+            WebRefresh,
+            WebSearch,
+            WebStop,
+            Yen,
+}
@@ -1,14 +1,25 @@
 use events::*;
-use std::cell::RefCell;
+// Names that `web_sys` exports too.
+use events::{Event, Touch};
+use gamepad::*;
+use gestures::GestureRecognizer;
+use handler::*;
+use js_sys::{Array, Reflect, Uint8Array};
+use replay::*;
+use stats::FrameStats;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
 use std::rc::Rc;
+use timing::FixedTimestep;
 use wasm_bindgen::closure::*;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
 use web_sys::*;
 use AppConfig;
-
-use std::cmp;
+use AppError;
+use Frame;
 
 #[wasm_bindgen]
 extern "C" {
@@ -30,166 +41,426 @@ extern "C" {
 
 // use web_sys::console;
 
-#[wasm_bindgen]
-/** Interface handle to event loop, from javascript, also provides for memory management
- * by keeping references to structures.
+/** Provides for memory management of a running app by keeping references to structures.
  */
-pub struct RenderLoopHandle {
+struct RenderLoopHandle {
     /** handle to inner render loop state structure */
     #[allow(dead_code)]
     render_loop: Rc<RefCell<RenderLoop>>,
     /** holds on to the closures until they can be dropped */
     #[allow(dead_code)]
-    closures: Vec<Box<Drop>>,
+    closures: Vec<Box<dyn Any>>,
 }
 
-impl RenderLoopHandle {
-    pub fn render_loop(&self) -> Rc<RefCell<RenderLoop>> {
-        self.render_loop.clone()
-    }
+thread_local! {
+    /// The render loops started by `App::run`. The browser drives them, so they are kept here
+    /// for as long as the page is open.
+    static RUNNING: RefCell<Vec<RenderLoopHandle>> = const { RefCell::new(Vec::new()) };
 }
 
 use render_loop::*;
 
 pub struct App {
+    /// The canvas of the current window.
     pub canvas: HtmlCanvasElement,
-    pub events: Vec<Event>,
-    pub _events: Rc<RefCell<Vec<Event>>>,
+    current: WindowId,
+    canvases: Rc<Canvases>,
+    pub events: Vec<TimedEvent>,
+    pub _events: Rc<RefCell<Vec<TimedEvent>>>,
+    pub input: InputState,
+    pub gamepads: Gamepads,
+    pub gestures: GestureRecognizer,
     pub render_loop: Option<Rc<RefCell<RenderLoop>>>,
+    text_input: HtmlTextAreaElement,
+    text_input_enabled: Rc<Cell<bool>>,
+    frame_time: f64,
+    frame: Option<Frame>,
+    /// Turns frame times into the fixed simulation steps of `Frame::steps`.
+    pub timestep: FixedTimestep,
+    /// Frame rate and frame time statistics.
+    pub stats: FrameStats,
+    recording: Option<Recording>,
+    player: Option<Player>,
+    pause_when_hidden: bool,
+    /// Page-wide event handlers, kept alive as long as the app.
+    _closures: Vec<Box<dyn Any>>,
 }
 
 impl App {
-    pub fn new(config: AppConfig) -> Result<(Rc<RefCell<App>>, RenderLoopHandle), JsValue> {
-        let window = window().expect("window not found");
-        let document = window.document().expect("document not found");
-
-        let mut closures: Vec<Box<Drop>> = vec![];
+    pub fn new(config: AppConfig) -> Result<App, AppError> {
+        config.validate()?;
+        let window = window().ok_or_else(|| AppError("window not found".to_string()))?;
+        let document = window
+            .document()
+            .ok_or_else(|| AppError("document not found".to_string()))?;
 
-        let canvas: HtmlCanvasElement = document
-            .create_element("canvas")
-            .expect("canvas creation failed")
-            .dyn_into::<HtmlCanvasElement>()
-            .expect("Not canvas");
+        let mut closures: Vec<Box<dyn Any>> = vec![];
 
-        let node: Node = document.body().expect("body").into();
-        node.append_child(&canvas.clone().into());
+        use events::{ElementState, KeyboardInput, WindowEvent};
 
-        canvas.set_width(config.size.0);
-        canvas.set_height(config.size.1);
+        let _events = Rc::new(RefCell::new(Vec::<TimedEvent>::new()));
 
-        use events::{ElementState, Event, KeyboardInput, WindowEvent};
-
-        let _events = Rc::new(RefCell::new(Vec::<Event>::new()));
+        let canvases = Rc::new(Canvases {
+            list: RefCell::new(Vec::new()),
+            events: _events.clone(),
+            focused: Cell::new(WindowId::PRIMARY),
+            hovered: Cell::new(None),
+            locked: Cell::new(None),
+            grab_requested: Cell::new(WindowId::PRIMARY),
+            next_id: Cell::new(WindowId::PRIMARY.0),
+            scale_factor: Cell::new(window.device_pixel_ratio()),
+            capture_wheel: config.capture_wheel,
+            follow_css_size: config.follow_css_size,
+        });
+        let (_, canvas) = add_canvas(&canvases, &config.title, config.size)?;
+        canvases.sync();
 
         let evs1 = _events.clone();
+        let canvases1 = canvases.clone();
 
         let onkeyup = Closure::wrap(Box::new(move |ev: KeyboardEvent| {
-            let input = KeyboardInput::from_keyboard_event(&ev, ElementState::Pressed);
+            let input = KeyboardInput::from_keyboard_event(&ev, ElementState::Released);
             let event = WindowEvent::KeyboardInput {
-                device_id: DeviceId,
+                device_id: DeviceId::KEYBOARD,
                 input,
             };
-            evs1.borrow_mut().push(Event::WindowEvent {
-                window_id: WindowId,
-                event,
-            });
-        }) as Box<FnMut(KeyboardEvent)>);
+            push_window_event(&evs1, ev.time_stamp(), canvases1.focused.get(), event);
+        }) as Box<dyn FnMut(KeyboardEvent)>);
 
-        window.set_onkeyup(Some(&onkeyup.as_ref().unchecked_ref()));
+        window.set_onkeyup(Some(onkeyup.as_ref().unchecked_ref()));
 
         let evs1 = _events.clone();
+        let canvases1 = canvases.clone();
 
         let onkeydown = Closure::wrap(Box::new(move |ev: KeyboardEvent| {
-            let input = KeyboardInput::from_keyboard_event(&ev, ElementState::Released);
+            let input = KeyboardInput::from_keyboard_event(&ev, ElementState::Pressed);
             let event = WindowEvent::KeyboardInput {
-                device_id: DeviceId,
+                device_id: DeviceId::KEYBOARD,
                 input,
             };
-            evs1.borrow_mut().push(Event::WindowEvent {
-                window_id: WindowId,
-                event,
-            });
-        }) as Box<FnMut(KeyboardEvent)>);
+            push_window_event(&evs1, ev.time_stamp(), canvases1.focused.get(), event);
+        }) as Box<dyn FnMut(KeyboardEvent)>);
+
+        window.set_onkeydown(Some(onkeydown.as_ref().unchecked_ref()));
+
+        // Pointer events are taken from the whole page and routed to the canvas they are for.
+        // Pressing a button or touching a canvas captures the pointer, so the release is
+        // delivered even if it happens off the canvas.
+        let canvases1 = canvases.clone();
+
+        let onpointerdown = Closure::wrap(Box::new(move |ev: PointerEvent| {
+            push_pointer_event(&canvases1, &ev, TouchPhase::Started);
+        }) as Box<dyn FnMut(PointerEvent)>);
 
-        window.set_onkeyup(Some(&onkeydown.as_ref().unchecked_ref()));
+        window.set_onpointerdown(Some(onpointerdown.as_ref().unchecked_ref()));
+
+        let canvases1 = canvases.clone();
+
+        let onpointermove = Closure::wrap(Box::new(move |ev: PointerEvent| {
+            push_pointer_event(&canvases1, &ev, TouchPhase::Moved);
+        }) as Box<dyn FnMut(PointerEvent)>);
+
+        window.set_onpointermove(Some(onpointermove.as_ref().unchecked_ref()));
+
+        let canvases1 = canvases.clone();
+
+        let onpointerup = Closure::wrap(Box::new(move |ev: PointerEvent| {
+            push_pointer_event(&canvases1, &ev, TouchPhase::Ended);
+        }) as Box<dyn FnMut(PointerEvent)>);
+
+        window.set_onpointerup(Some(onpointerup.as_ref().unchecked_ref()));
+
+        let canvases1 = canvases.clone();
+
+        let onpointercancel = Closure::wrap(Box::new(move |ev: PointerEvent| {
+            push_pointer_event(&canvases1, &ev, TouchPhase::Cancelled);
+        }) as Box<dyn FnMut(PointerEvent)>);
+
+        window.set_onpointercancel(Some(onpointercancel.as_ref().unchecked_ref()));
+
+        let text_input_enabled = Rc::new(Cell::new(false));
 
         let evs1 = _events.clone();
+        let canvases1 = canvases.clone();
+        let enabled = text_input_enabled.clone();
 
-        let onmousedown = Closure::wrap(Box::new(move |ev: MouseEvent| {
-            use events::MouseButton;
-            let event = WindowEvent::MouseInput {
-                device_id: DeviceId,
-                state: ElementState::Pressed,
-                button: MouseButton::from_mouse_button(ev.button()),
-                modifiers: ModifiersState::default(),
+        // Without an IME, typed characters come from keypress. While text input is enabled they
+        // come from the hidden text area below instead.
+        let onkeypress = Closure::wrap(Box::new(move |ev: KeyboardEvent| {
+            if enabled.get() {
+                return;
+            }
+            let key = ev.key();
+            let mut chars = key.chars();
+            let c = match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ if key == "Enter" => '\r',
+                _ => return,
             };
-            evs1.borrow_mut().push(Event::WindowEvent {
-                window_id: WindowId,
-                event,
-            });
-        }) as Box<FnMut(MouseEvent)>);
+            let event = WindowEvent::ReceivedCharacter(c);
+            push_window_event(&evs1, ev.time_stamp(), canvases1.focused.get(), event);
+        }) as Box<dyn FnMut(KeyboardEvent)>);
 
-        window.set_onmousedown(Some(&onmousedown.as_ref().unchecked_ref()));
+        window.set_onkeypress(Some(onkeypress.as_ref().unchecked_ref()));
+
+        // Input methods only work on editable elements, so text input goes through an invisible
+        // text area that gets focus while text input is enabled.
+        let text_input = document
+            .create_element("textarea")?
+            .dyn_into::<HtmlTextAreaElement>()
+            .map_err(JsValue::from)?;
+        text_input.set_attribute("autocapitalize", "off")?;
+        text_input.set_attribute("autocomplete", "off")?;
+        text_input.set_attribute("spellcheck", "false")?;
+        text_input.set_attribute("style", &text_input_style(0.0, 0.0))?;
+        let node: Node = document
+            .body()
+            .ok_or_else(|| AppError("document has no body".to_string()))?
+            .into();
+        node.append_child(&text_input.clone().into())?;
 
         let evs1 = _events.clone();
+        let canvases1 = canvases.clone();
+        let textarea = text_input.clone();
 
-        let onmouseup = Closure::wrap(Box::new(move |ev: MouseEvent| {
-            use events::MouseButton;
-            let event = WindowEvent::MouseInput {
-                device_id: DeviceId,
-                state: ElementState::Released,
-                button: MouseButton::from_mouse_button(ev.button()),
-                modifiers: ModifiersState::default(),
-            };
-            evs1.borrow_mut().push(Event::WindowEvent {
-                window_id: WindowId,
-                event,
-            });
-        }) as Box<FnMut(MouseEvent)>);
+        let onbeforeinput = Closure::wrap(Box::new(move |ev: InputEvent| {
+            if ev.is_composing() {
+                return;
+            }
+            let (time, id) = (ev.time_stamp(), canvases1.focused.get());
+            if let Some(data) = ev.data() {
+                for c in data.chars() {
+                    push_window_event(&evs1, time, id, WindowEvent::ReceivedCharacter(c));
+                }
+            } else if ev.input_type() == "insertLineBreak" {
+                push_window_event(&evs1, time, id, WindowEvent::ReceivedCharacter('\r'));
+            }
+            // Nothing needs to be kept in the text area itself.
+            ev.prevent_default();
+            textarea.set_value("");
+        }) as Box<dyn FnMut(InputEvent)>);
 
-        window.set_onmouseup(Some(&onmouseup.as_ref().unchecked_ref()));
+        text_input.add_event_listener_with_callback(
+            "beforeinput",
+            onbeforeinput.as_ref().unchecked_ref(),
+        )?;
 
         let evs1 = _events.clone();
+        let canvases1 = canvases.clone();
 
-        let onmousemove = Closure::wrap(Box::new(move |ev: MouseEvent| {
-            let event = WindowEvent::CursorMoved {
-                device_id: DeviceId,
-                position: (ev.client_x() as _, ev.client_y() as _),
-                modifiers: ModifiersState::default(),
-            };
-            evs1.borrow_mut().push(Event::WindowEvent {
-                window_id: WindowId,
-                event,
-            });
-        }) as Box<FnMut(MouseEvent)>);
+        let oncompositionupdate = Closure::wrap(Box::new(move |ev: CompositionEvent| {
+            // The DOM does not expose the cursor within the composition, so place it at the end.
+            let text = ev.data().unwrap_or_default();
+            let cursor = text.len();
+            push_window_event(
+                &evs1,
+                ev.time_stamp(),
+                canvases1.focused.get(),
+                WindowEvent::Ime(Ime::Preedit(text, Some((cursor, cursor)))),
+            );
+        }) as Box<dyn FnMut(CompositionEvent)>);
+
+        text_input.add_event_listener_with_callback(
+            "compositionupdate",
+            oncompositionupdate.as_ref().unchecked_ref(),
+        )?;
+
+        let evs1 = _events.clone();
+        let canvases1 = canvases.clone();
+        let textarea = text_input.clone();
+
+        let oncompositionend = Closure::wrap(Box::new(move |ev: CompositionEvent| {
+            let (time, id) = (ev.time_stamp(), canvases1.focused.get());
+            push_window_event(
+                &evs1,
+                time,
+                id,
+                WindowEvent::Ime(Ime::Preedit(String::new(), None)),
+            );
+            if let Some(text) = ev.data().filter(|text| !text.is_empty()) {
+                push_window_event(&evs1, time, id, WindowEvent::Ime(Ime::Commit(text)));
+            }
+            textarea.set_value("");
+        }) as Box<dyn FnMut(CompositionEvent)>);
+
+        text_input.add_event_listener_with_callback(
+            "compositionend",
+            oncompositionend.as_ref().unchecked_ref(),
+        )?;
+
+        let enabled = text_input_enabled.clone();
+        let textarea = text_input.clone();
+
+        // Clicking a canvas takes the focus from the text area while text input is still
+        // enabled, which would lose the typed text. Focus that moves to another element of the
+        // page is left there.
+        let ontextinputblur = Closure::wrap(Box::new(move |ev: FocusEvent| {
+            if enabled.get() && ev.related_target().is_none() {
+                let _ = textarea.focus();
+            }
+        }) as Box<dyn FnMut(FocusEvent)>);
+
+        text_input
+            .add_event_listener_with_callback("blur", ontextinputblur.as_ref().unchecked_ref())?;
+
+        let enabled = text_input_enabled.clone();
+        let textarea = text_input.clone();
+
+        // Browsers only bring up the on-screen keyboard for focus given during a user gesture.
+        let ontextinputpointerdown = Closure::wrap(Box::new(move || {
+            if enabled.get() {
+                let _ = textarea.focus();
+            }
+        }) as Box<dyn FnMut()>);
+
+        window.add_event_listener_with_callback(
+            "pointerdown",
+            ontextinputpointerdown.as_ref().unchecked_ref(),
+        )?;
+
+        let canvases1 = canvases.clone();
 
-        window.set_onmousemove(Some(&onmousemove.as_ref().unchecked_ref()));
+        let onresize = Closure::wrap(Box::new(move || {
+            canvases1.sync();
+        }) as Box<dyn FnMut()>);
+
+        window.set_onresize(Some(onresize.as_ref().unchecked_ref()));
+
+        let evs1 = _events.clone();
+        let canvases1 = canvases.clone();
+        let document1 = document.clone();
+
+        let onpointerlockchange = Closure::wrap(Box::new(move || {
+            let element: Option<Element> = document1.pointer_lock_element();
+            let locked = element.and_then(|element| canvases1.find(&element));
+            let previous = canvases1.locked.replace(locked);
+            if previous == locked {
+                return;
+            }
+            if let Some(id) = previous {
+                push_window_event(&evs1, now(), id, WindowEvent::CursorGrabChanged(false));
+            }
+            if let Some(id) = locked {
+                push_window_event(&evs1, now(), id, WindowEvent::CursorGrabChanged(true));
+            }
+        }) as Box<dyn FnMut()>);
+
+        document.set_onpointerlockchange(Some(onpointerlockchange.as_ref().unchecked_ref()));
+
+        let evs1 = _events.clone();
+        let canvases1 = canvases.clone();
+
+        let onpointerlockerror = Closure::wrap(Box::new(move || {
+            let id = canvases1.grab_requested.get();
+            push_window_event(&evs1, now(), id, WindowEvent::CursorGrabFailed);
+        }) as Box<dyn FnMut()>);
+
+        document.set_onpointerlockerror(Some(onpointerlockerror.as_ref().unchecked_ref()));
 
         let evs1 = _events.clone();
+        let canvases1 = canvases.clone();
+
+        let onfocus = Closure::wrap(Box::new(move || {
+            let id = canvases1.focused.get();
+            push_window_event(&evs1, now(), id, WindowEvent::Focused(true));
+        }) as Box<dyn FnMut()>);
+
+        window.set_onfocus(Some(onfocus.as_ref().unchecked_ref()));
+
+        let evs1 = _events.clone();
+        let canvases1 = canvases.clone();
+
+        let onblur = Closure::wrap(Box::new(move || {
+            let id = canvases1.focused.get();
+            push_window_event(&evs1, now(), id, WindowEvent::Focused(false));
+        }) as Box<dyn FnMut()>);
+
+        window.set_onblur(Some(onblur.as_ref().unchecked_ref()));
 
         closures.push(Box::new(onkeydown));
         closures.push(Box::new(onkeyup));
-        closures.push(Box::new(onmousedown));
-        closures.push(Box::new(onmouseup));
-        closures.push(Box::new(onmousemove));
+        closures.push(Box::new(onpointerdown));
+        closures.push(Box::new(onpointermove));
+        closures.push(Box::new(onpointerup));
+        closures.push(Box::new(onpointercancel));
+        closures.push(Box::new(onkeypress));
+        closures.push(Box::new(onbeforeinput));
+        closures.push(Box::new(oncompositionupdate));
+        closures.push(Box::new(oncompositionend));
+        closures.push(Box::new(ontextinputblur));
+        closures.push(Box::new(ontextinputpointerdown));
+        closures.push(Box::new(onresize));
+        closures.push(Box::new(onpointerlockchange));
+        closures.push(Box::new(onpointerlockerror));
+        closures.push(Box::new(onfocus));
+        closures.push(Box::new(onblur));
+
+        let (recording, player) = match config.run_mode {
+            RunMode::Live => (None, None),
+            RunMode::Record { .. } => (Some(Recording::new()), None),
+            RunMode::Replay { recording, .. } => (None, Some(Player::new(recording))),
+        };
 
-        let app = Rc::new(RefCell::new(App {
+        Ok(App {
             canvas,
-            //   window: canvas,
+            current: WindowId::PRIMARY,
+            canvases,
             events: Vec::new(),
             _events,
+            input: InputState::new(),
+            gamepads: Gamepads::new(Box::new(WebGamepads::new(window.navigator()))),
+            gestures: GestureRecognizer::default(),
             render_loop: None,
-        }));
+            text_input,
+            text_input_enabled,
+            frame_time: 0.0,
+            frame: None,
+            timestep: FixedTimestep::new(config.fixed_rate),
+            stats: FrameStats::default(),
+            recording,
+            player,
+            pause_when_hidden: config.pause_when_hidden,
+            _closures: closures,
+        })
+    }
+
+    /// Calls `callback` once per animation frame, from `requestAnimationFrame`.
+    ///
+    /// Returns right away and leaves the loop to the browser; the app is kept alive as long as
+    /// the page. `render_loop` can pause and resume it. When replaying, the loop stops at the
+    /// end of the recording.
+    pub fn run<F>(self, callback: F)
+    where
+        F: 'static + FnMut(&mut App, &Frame),
+    {
+        self.start_render_loop(Box::new(callback), None);
+    }
 
-        let mut app1 = app.clone();
+    /// Runs `handler` like `run` runs a callback. Its `exit` hook is called when the page is
+    /// unloaded, but not when it is only put in the back/forward cache, and when a replay ends.
+    pub fn run_handler<H>(self, handler: H)
+    where
+        H: 'static + ApplicationHandler,
+    {
+        let driver = Rc::new(RefCell::new(HandlerDriver::new(handler)));
+        let driver1 = driver.clone();
+        self.start_render_loop(
+            Box::new(move |app: &mut App, frame: &Frame| driver1.borrow_mut().frame(app, frame)),
+            Some(Box::new(move |app: &mut App| driver.borrow_mut().exit(app))),
+        );
+    }
 
-        //   app.closures.push(Box::new(onmousemove));
+    fn start_render_loop(self, callback: FrameCallback, exit: Option<ExitCallback>) {
+        let window = window().expect("window not found");
+        let document = window.document().expect("document not found");
+        let pause_when_hidden = self.pause_when_hidden;
+        let app = Rc::new(RefCell::new(self));
 
         // Render loop handling
         let render_loop: Rc<RefCell<RenderLoop>> = Rc::new(RefCell::new(RenderLoop::new(
             window.clone(),
             app.clone(),
-            None,
+            Some(callback),
         )));
 
         app.borrow_mut().render_loop = Some(render_loop.clone());
@@ -197,34 +468,268 @@ impl App {
         render_loop.borrow_mut().closure = Some({
             let render_loop = render_loop.clone();
             Closure::wrap(Box::new(move |time: f64| {
-                render_loop.borrow_mut().render_loop(time);
+                RenderLoop::render_loop(&render_loop, time);
             }))
         });
 
-        //     render_loop.borrow_mut().play()?;
+        render_loop.borrow_mut().pause_when_hidden = pause_when_hidden;
+        render_loop.borrow_mut().exit = exit;
+
+        // Page lifecycle: hidden tabs and pages put in the back/forward cache are suspended.
+        let suspended = Rc::new(Cell::new(document.hidden()));
+        let set_suspended = {
+            let events = app.borrow()._events.clone();
+            let render_loop = render_loop.clone();
+            move |hidden: bool| {
+                if suspended.replace(hidden) == hidden {
+                    return;
+                }
+                events
+                    .borrow_mut()
+                    .push(TimedEvent::new(now(), Event::Suspended(hidden)));
+                if render_loop.try_borrow_mut().is_ok() {
+                    let _ = RenderLoop::visibility_changed(&render_loop, hidden);
+                }
+            }
+        };
+
+        let set_suspended1 = set_suspended.clone();
+        let document1 = document.clone();
+
+        let onvisibilitychange = Closure::wrap(Box::new(move || {
+            set_suspended1(document1.hidden());
+        }) as Box<dyn FnMut()>);
+
+        document.set_onvisibilitychange(Some(onvisibilitychange.as_ref().unchecked_ref()));
+
+        let set_suspended1 = set_suspended.clone();
+        let render_loop1 = render_loop.clone();
+
+        let onpagehide = Closure::wrap(Box::new(move |ev: PageTransitionEvent| {
+            set_suspended1(true);
+            // Pages that are not kept in the back/forward cache are gone for good.
+            if !ev.persisted() {
+                if let Ok(mut render_loop) = render_loop1.try_borrow_mut() {
+                    render_loop.exit();
+                }
+            }
+        }) as Box<dyn FnMut(PageTransitionEvent)>);
 
-        Ok((
-            app,
-            RenderLoopHandle {
+        window.set_onpagehide(Some(onpagehide.as_ref().unchecked_ref()));
+
+        let set_suspended1 = set_suspended.clone();
+        let document1 = document.clone();
+
+        let onpageshow = Closure::wrap(Box::new(move || {
+            set_suspended1(document1.hidden());
+        }) as Box<dyn FnMut()>);
+
+        window.set_onpageshow(Some(onpageshow.as_ref().unchecked_ref()));
+
+        let closures: Vec<Box<dyn Any>> = vec![
+            Box::new(onvisibilitychange),
+            Box::new(onpagehide),
+            Box::new(onpageshow),
+        ];
+
+        if let Err(err) = render_loop.borrow_mut().play() {
+            log_many("could not start the render loop", &format!("{:?}", err));
+        }
+        RUNNING.with(|running| {
+            running.borrow_mut().push(RenderLoopHandle {
                 render_loop,
                 closures,
+            })
+        });
+    }
+
+    /// Moves the events queued by the DOM handlers into `events`, polls the gamepads and
+    /// updates `input`.
+    ///
+    /// When replaying, `events` gets the next recorded frame instead and live input is dropped.
+    /// Returns `None` at the end of the recording.
+    pub(crate) fn begin_frame(&mut self, time: f64) -> Option<Frame> {
+        // Layout changes and moves to a monitor with another pixel ratio fire no resize event.
+        self.canvases.sync();
+        let live: Vec<TimedEvent> = self._events.borrow_mut().drain(..).collect();
+        match self.player {
+            Some(ref mut player) => match player.next_frame() {
+                Some(frame) => {
+                    self.frame_time = frame.time;
+                    self.events = frame.events;
+                }
+                None => return None,
             },
-        ))
+            None => {
+                self.frame_time = time;
+                self.events = live;
+                let mut polled = Vec::new();
+                self.gamepads.poll(&mut polled);
+                self.events
+                    .extend(polled.into_iter().map(|event| TimedEvent::new(time, event)));
+            }
+        }
+        if let Some(ref mut recording) = self.recording {
+            recording.push(self.frame_time, &self.events);
+        }
+        self.input.update(&self.events);
+        self.gestures.update(self.frame_time, &self.events);
+        let frame = Frame::after(self.frame, self.frame_time, &mut self.timestep);
+        self.frame = Some(frame);
+        self.stats.frame_started(&frame);
+        Some(frame)
+    }
 
-        //   app
-        /*
-        Rc::new(RefCell::new(App {
-            //   window: canvas,
-            events: Vec::new(),
-            _events: Rc::new(RefCell::new(Vec::new())),
-            callback: None,
-            render_loop: None,
-        }))*/    }
+    /// Restarts frame timing at `time` after the render loop was paused, so the next frame's
+    /// `delta` and simulation steps leave out the time spent paused.
+    ///
+    /// Replayed frames keep their recorded times.
+    pub(crate) fn resume_frames(&mut self, time: f64) {
+        if self.player.is_some() {
+            return;
+        }
+        self.timestep.reset();
+        self.stats.resumed();
+        if let Some(ref mut frame) = self.frame {
+            frame.time = time;
+        }
+    }
+
+    /// Time at the start of the current frame, in milliseconds: the animation frame time, which
+    /// counts from page load like `performance.now()`.
+    ///
+    /// When replaying this is the recorded time, counted from the first recorded frame.
+    pub fn frame_time(&self) -> f64 {
+        self.frame_time
+    }
+
+    /// The frames recorded so far, in `RunMode::Record`.
+    pub fn recording(&self) -> Option<&Recording> {
+        self.recording.as_ref()
+    }
+
+    /// Adds another canvas to the page, with its own WebGL context, and returns its id.
+    ///
+    /// Events of the canvas carry the id. Keyboard and text input go to the canvas that was
+    /// last clicked or touched. The title becomes the canvas's accessible name.
+    pub fn create_window(&mut self, title: &str, size: (u32, u32)) -> Result<WindowId, AppError> {
+        let (id, _) = add_canvas(&self.canvases, title, size)?;
+        self.canvases.sync();
+        Ok(id)
+    }
+
+    /// Removes a canvas from the page. If it was the current window, the first remaining one
+    /// becomes current.
+    pub fn close_window(&mut self, id: WindowId) {
+        self.canvases.remove(id);
+        if self.current == id {
+            if let Some(first) = self.windows().first() {
+                self.set_current_window(*first);
+            }
+        }
+    }
+
+    /// Ids of the canvases, the primary one first.
+    pub fn windows(&self) -> Vec<WindowId> {
+        self.canvases
+            .list
+            .borrow()
+            .iter()
+            .map(|window| window.id)
+            .collect()
+    }
+
+    /// The window that `canvas`, `gl` and the other window methods of `App` apply to.
+    pub fn current_window(&self) -> WindowId {
+        self.current
+    }
+
+    /// Switches the current window. Returns false if there is no such window.
+    pub fn set_current_window(&mut self, id: WindowId) -> bool {
+        match self.canvases.get(id) {
+            Some((id, canvas)) => {
+                self.current = id;
+                self.canvas = canvas;
+                true
+            }
+            None => false,
+        }
+    }
 
     pub fn canvas(&self) -> &HtmlCanvasElement {
         &self.canvas
     }
 
+    /// Size of the canvas backing store, in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.canvas.width(), self.canvas.height())
+    }
+
+    /// Converts a position in canvas pixels, like `CursorMoved.position`, to CSS pixels from
+    /// the top-left corner of the canvas.
+    ///
+    /// Uses the size of the current canvas. With several canvases, switch to the event's
+    /// `window_id` with `set_current_window` first.
+    pub fn to_logical(&self, position: (f64, f64)) -> (f64, f64) {
+        let rect = self.canvas.get_bounding_client_rect();
+        let scale = |css: f64, pixels: u32| if pixels > 0 { css / pixels as f64 } else { 1.0 };
+        (
+            position.0 * scale(rect.width(), self.canvas.width()),
+            position.1 * scale(rect.height(), self.canvas.height()),
+        )
+    }
+
+    /// Ratio of canvas pixels to CSS pixels (`devicePixelRatio`).
+    pub fn hidpi_factor(&self) -> f64 {
+        self.canvases.scale_factor.get()
+    }
+
+    /// Grabs or releases the cursor using the Pointer Lock API.
+    ///
+    /// While grabbed the cursor is hidden and mouse movement arrives as
+    /// `DeviceEvent::MouseMotion`. The outcome is reported with `CursorGrabChanged` or
+    /// `CursorGrabFailed`. Browsers only grant the lock in response to a user gesture, so call
+    /// this while handling a click or key press.
+    pub fn set_cursor_grab(&mut self, grab: bool) {
+        if grab {
+            self.canvases.grab_requested.set(self.current);
+            self.canvas.request_pointer_lock();
+        } else if let Some(document) = self.canvas.owner_document() {
+            document.exit_pointer_lock();
+        }
+    }
+
+    /// Starts or stops text input.
+    ///
+    /// While enabled, typed text arrives as `ReceivedCharacter` events and input method
+    /// compositions as `Ime` events. Enable it while an in-app text field has focus; on mobile
+    /// browsers this also brings up the on-screen keyboard.
+    pub fn set_text_input_enabled(&mut self, enabled: bool) {
+        if self.text_input_enabled.replace(enabled) == enabled {
+            return;
+        }
+        let ime = if enabled {
+            let _ = self.text_input.focus();
+            Ime::Enabled
+        } else {
+            let _ = self.text_input.blur();
+            self.text_input.set_value("");
+            Ime::Disabled
+        };
+        let id = self.canvases.focused.get();
+        push_window_event(&self._events, now(), id, WindowEvent::Ime(ime));
+    }
+
+    /// Moves the input method candidate window next to the given position, in canvas pixels.
+    pub fn set_ime_position(&mut self, position: (f64, f64)) {
+        let rect = self.canvas.get_bounding_client_rect();
+        let (x, y) = self.to_logical(position);
+        let (left, top) = (rect.left() + x, rect.top() + y);
+        let _ = self
+            .text_input
+            .set_attribute("style", &text_input_style(left, top));
+    }
+
     pub fn gl(&self) -> WebGl2RenderingContext {
         self.canvas()
             .get_context("webgl2")
@@ -235,18 +740,579 @@ impl App {
     }
 }
 
+/// The canvases of the app, shared with the DOM handlers that route events to them.
+struct Canvases {
+    list: RefCell<Vec<CanvasWindow>>,
+    events: Rc<RefCell<Vec<TimedEvent>>>,
+    /// The canvas that gets keyboard, focus and text input: the one last clicked or touched.
+    focused: Cell<WindowId>,
+    /// The canvas the mouse or pen is over.
+    hovered: Cell<Option<WindowId>>,
+    /// The canvas holding the pointer lock.
+    locked: Cell<Option<WindowId>>,
+    /// The canvas the pointer lock was last requested for.
+    grab_requested: Cell<WindowId>,
+    next_id: Cell<u32>,
+    scale_factor: Cell<f64>,
+    capture_wheel: bool,
+    follow_css_size: bool,
+}
+
+struct CanvasWindow {
+    id: WindowId,
+    canvas: HtmlCanvasElement,
+    /// The canvas's own event handlers, kept alive as long as the canvas.
+    _closures: Vec<Box<dyn Any>>,
+}
+
+impl Canvases {
+    fn get(&self, id: WindowId) -> Option<(WindowId, HtmlCanvasElement)> {
+        self.list
+            .borrow()
+            .iter()
+            .find(|window| window.id == id)
+            .map(|window| (window.id, window.canvas.clone()))
+    }
+
+    /// The canvas that is `element`.
+    fn find(&self, element: &Element) -> Option<WindowId> {
+        self.list
+            .borrow()
+            .iter()
+            .find(|window| {
+                let canvas: &Element = window.canvas.as_ref();
+                canvas == element
+            })
+            .map(|window| window.id)
+    }
+
+    /// Removes a canvas from the page and drops its handlers.
+    fn remove(&self, id: WindowId) {
+        let window = {
+            let mut list = self.list.borrow_mut();
+            match list.iter().position(|window| window.id == id) {
+                Some(index) => list.remove(index),
+                None => return,
+            }
+        };
+        let canvas = &window.canvas;
+        canvas.set_onwheel(None);
+        canvas.set_ondragenter(None);
+        canvas.set_ondragover(None);
+        canvas.set_ondragleave(None);
+        canvas.set_ondrop(None);
+        canvas.remove();
+
+        for state in [&self.hovered, &self.locked].iter() {
+            if state.get() == Some(id) {
+                state.set(None);
+            }
+        }
+        if self.focused.get() == id {
+            if let Some(first) = self.list.borrow().first() {
+                self.focused.set(first.id);
+            }
+        }
+    }
+
+    /// Keeps track of the device pixel ratio and, if enabled, keeps the canvas backing stores
+    /// in step with their CSS size.
+    fn sync(&self) {
+        let scale_factor = window().map_or(1.0, |window| window.device_pixel_ratio());
+        let scale_changed = self.scale_factor.replace(scale_factor) != scale_factor;
+
+        for window in self.list.borrow().iter() {
+            if scale_changed {
+                push_window_event(
+                    &self.events,
+                    now(),
+                    window.id,
+                    WindowEvent::HiDPIFactorChanged(scale_factor as f32),
+                );
+            }
+
+            if self.follow_css_size {
+                let canvas = &window.canvas;
+                let rect = canvas.get_bounding_client_rect();
+                let width = (rect.width() * scale_factor).round() as u32;
+                let height = (rect.height() * scale_factor).round() as u32;
+                if width != canvas.width() || height != canvas.height() {
+                    canvas.set_width(width);
+                    canvas.set_height(height);
+                    let event = WindowEvent::Resized(width, height);
+                    push_window_event(&self.events, now(), window.id, event);
+                }
+            }
+        }
+    }
+}
+
+/// Creates a canvas, adds it to the page and registers the handlers of events that target the
+/// canvas itself.
+fn add_canvas(
+    canvases: &Canvases,
+    title: &str,
+    size: (u32, u32),
+) -> Result<(WindowId, HtmlCanvasElement), JsValue> {
+    let document = window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("document not found"))?;
+    let canvas = document
+        .create_element("canvas")?
+        .dyn_into::<HtmlCanvasElement>()?;
+
+    let node: Node = document
+        .body()
+        .ok_or_else(|| JsValue::from_str("document has no body"))?
+        .into();
+    node.append_child(&canvas.clone().into())?;
+
+    canvas.set_width(size.0);
+    canvas.set_height(size.1);
+    // Touches go to the app instead of scrolling or zooming the page.
+    let mut style = String::from("touch-action: none;");
+    if canvases.follow_css_size {
+        style += &format!(" width: {}px; height: {}px;", size.0, size.1);
+    }
+    canvas.set_attribute("style", &style)?;
+    canvas.set_attribute("aria-label", title)?;
+
+    let id = WindowId(canvases.next_id.get());
+    canvases.next_id.set(id.0 + 1);
+
+    let evs1 = canvases.events.clone();
+    let capture_wheel = canvases.capture_wheel;
+
+    let onwheel = Closure::wrap(Box::new(move |ev: WheelEvent| {
+        if capture_wheel {
+            ev.prevent_default();
+        }
+        // The DOM reports scrolling down as positive, `MouseScrollDelta` as negative.
+        let (x, y) = (-ev.delta_x() as f32, -ev.delta_y() as f32);
+        let delta = match ev.delta_mode() {
+            WheelEvent::DOM_DELTA_PIXEL => MouseScrollDelta::PixelDelta(x, y),
+            WheelEvent::DOM_DELTA_LINE => MouseScrollDelta::LineDelta(x, y),
+            _ => MouseScrollDelta::LineDelta(x * LINES_PER_PAGE, y * LINES_PER_PAGE),
+        };
+        push_window_event(
+            &evs1,
+            ev.time_stamp(),
+            id,
+            WindowEvent::MouseWheel {
+                device_id: DeviceId::MOUSE,
+                delta,
+                phase: TouchPhase::Moved,
+                modifiers: ModifiersState::from_mouse_event(&ev),
+            },
+        );
+    }) as Box<dyn FnMut(WheelEvent)>);
+
+    canvas.set_onwheel(Some(onwheel.as_ref().unchecked_ref()));
+
+    let evs1 = canvases.events.clone();
+
+    let ondragenter = Closure::wrap(Box::new(move |ev: DragEvent| {
+        ev.prevent_default();
+        // Browsers only reveal file names on drop.
+        push_window_event(
+            &evs1,
+            ev.time_stamp(),
+            id,
+            WindowEvent::HoveredFile(PathBuf::new()),
+        );
+    }) as Box<dyn FnMut(DragEvent)>);
+
+    canvas.set_ondragenter(Some(ondragenter.as_ref().unchecked_ref()));
+
+    // Accepting dragover is what allows dropping onto the canvas.
+    let ondragover = Closure::wrap(Box::new(move |ev: DragEvent| {
+        ev.prevent_default();
+    }) as Box<dyn FnMut(DragEvent)>);
+
+    canvas.set_ondragover(Some(ondragover.as_ref().unchecked_ref()));
+
+    let evs1 = canvases.events.clone();
+
+    let ondragleave = Closure::wrap(Box::new(move |ev: DragEvent| {
+        let event = WindowEvent::HoveredFileCancelled;
+        push_window_event(&evs1, ev.time_stamp(), id, event);
+    }) as Box<dyn FnMut(DragEvent)>);
+
+    canvas.set_ondragleave(Some(ondragleave.as_ref().unchecked_ref()));
+
+    let evs1 = canvases.events.clone();
+
+    let ondrop = Closure::wrap(Box::new(move |ev: DragEvent| {
+        ev.prevent_default();
+        let files = match ev.data_transfer().and_then(|data| data.files()) {
+            Some(files) => files,
+            None => return,
+        };
+        for i in 0..files.length() {
+            if let Some(file) = files.get(i) {
+                let path = PathBuf::from(file.name());
+                let event = WindowEvent::DroppedFile(path);
+                push_window_event(&evs1, ev.time_stamp(), id, event);
+                read_dropped_file(&file, id, &evs1);
+            }
+        }
+    }) as Box<dyn FnMut(DragEvent)>);
+
+    canvas.set_ondrop(Some(ondrop.as_ref().unchecked_ref()));
+
+    let closures: Vec<Box<dyn Any>> = vec![
+        Box::new(onwheel),
+        Box::new(ondragenter),
+        Box::new(ondragover),
+        Box::new(ondragleave),
+        Box::new(ondrop),
+    ];
+    canvases.list.borrow_mut().push(CanvasWindow {
+        id,
+        canvas: canvas.clone(),
+        _closures: closures,
+    });
+    Ok((id, canvas))
+}
+
+/// How many lines a page-sized wheel step scrolls, for `WheelEvent.DOM_DELTA_PAGE`.
+const LINES_PER_PAGE: f32 = 20.0;
+
+fn push_window_event(
+    events: &Rc<RefCell<Vec<TimedEvent>>>,
+    time: f64,
+    window_id: WindowId,
+    event: WindowEvent,
+) {
+    let event = Event::WindowEvent { window_id, event };
+    events.borrow_mut().push(TimedEvent::new(time, event));
+}
+
+/// `performance.now()`, for events that come without a DOM event to take the time from. DOM
+/// event time stamps and animation frame times use the same clock.
+fn now() -> f64 {
+    window()
+        .and_then(|window| window.performance())
+        .map_or(0.0, |performance| performance.now())
+}
+
+/// Style of the text area used for text input: invisible, but placed where the input method
+/// should show its candidates.
+fn text_input_style(left: f64, top: f64) -> String {
+    format!(
+        "position: fixed; left: {}px; top: {}px; width: 1px; height: 1px; opacity: 0; \
+         border: 0; padding: 0; margin: 0; resize: none; overflow: hidden; \
+         pointer-events: none;",
+        left, top
+    )
+}
+
+/// Reads a dropped file in the background and reports it as `DroppedFileContents` once loaded,
+/// or as `DroppedFileFailed` if reading fails or is aborted.
+fn read_dropped_file(file: &File, window_id: WindowId, events: &Rc<RefCell<Vec<TimedEvent>>>) {
+    let failed = |name: String, error: String| {
+        log_many(
+            "could not read dropped file",
+            &format!("{}: {}", name, error),
+        );
+        WindowEvent::DroppedFileFailed { name, error }
+    };
+    let reader = match FileReader::new() {
+        Ok(reader) => reader,
+        Err(err) => {
+            let event = failed(file.name(), format!("{:?}", err));
+            push_window_event(events, now(), window_id, event);
+            return;
+        }
+    };
+    let (name, mime) = (file.name(), file.type_());
+    let events1 = events.clone();
+    let reader1 = reader.clone();
+    // loadend follows load, error and abort alike, so the closure is called exactly once.
+    let onloadend = Closure::once_into_js(move || {
+        let event = match (reader1.error(), reader1.result()) {
+            (None, Ok(buffer)) if !buffer.is_null() => {
+                let data = Uint8Array::new(&buffer).to_vec();
+                WindowEvent::DroppedFileContents(FileContents { name, mime, data })
+            }
+            (Some(error), _) => failed(name, error.message()),
+            (None, _) => failed(name, "no contents".to_string()),
+        };
+        push_window_event(&events1, now(), window_id, event);
+    });
+    reader.set_onloadend(Some(onloadend.unchecked_ref()));
+    if let Err(err) = reader.read_as_array_buffer(file) {
+        reader.set_onloadend(None);
+        let event = failed(file.name(), format!("{:?}", err));
+        push_window_event(events, now(), window_id, event);
+    }
+}
+
+/// Converts viewport coordinates of a DOM event into backing-store pixels relative to the
+/// top-left corner of the canvas, like the window-relative pixels of the native backend.
+fn canvas_position(canvas: &HtmlCanvasElement, client_x: f64, client_y: f64) -> (f64, f64) {
+    let rect = canvas.get_bounding_client_rect();
+    let scale = |pixels: u32, css: f64| if css > 0.0 { pixels as f64 / css } else { 1.0 };
+    (
+        (client_x - rect.left()) * scale(canvas.width(), rect.width()),
+        (client_y - rect.top()) * scale(canvas.height(), rect.height()),
+    )
+}
+
+/// Turns a DOM pointer event into touch events for touch contacts, and into mouse button and
+/// cursor events for mice and pens.
+///
+/// `phase` tells which DOM event it is: `Started` for pointerdown, `Moved` for pointermove,
+/// `Ended` for pointerup and `Cancelled` for pointercancel.
+fn push_pointer_event(canvases: &Canvases, ev: &PointerEvent, phase: TouchPhase) {
+    use events::MouseButton;
+
+    let time = ev.time_stamp();
+    let device_id = DeviceId::from_pointer_event(ev);
+    // Locked pointers target their canvas wherever they are, and the browser retargets captured
+    // ones to their canvas. Anything else only counts when it targets a canvas, so HTML controls
+    // laid over a canvas keep their pointer events to themselves.
+    let target = canvases.locked.get().or_else(|| {
+        ev.target()
+            .and_then(|target| target.dyn_into::<Element>().ok())
+            .and_then(|element| canvases.find(&element))
+    });
+    let target = target.and_then(|id| canvases.get(id));
+
+    if phase == TouchPhase::Started {
+        if let Some((id, ref canvas)) = target {
+            canvases.focused.set(id);
+            // Fails if the pointer is already gone, which needs no capture anyway.
+            let _ = canvas.set_pointer_capture(ev.pointer_id());
+        }
+    }
+
+    // Browsers may see several moves between two events; `getCoalescedEvents` has all of them,
+    // oldest first. Browsers without it only report the last one.
+    let has_coalesced = Reflect::has(ev.as_ref(), &JsValue::from_str("getCoalescedEvents"));
+    let samples: Vec<PointerEvent> = match has_coalesced {
+        Ok(true) if phase == TouchPhase::Moved => ev
+            .get_coalesced_events()
+            .iter()
+            .filter_map(|sample| sample.dyn_into().ok())
+            .collect(),
+        _ => Vec::new(),
+    };
+    let samples = if samples.is_empty() {
+        vec![ev.clone()]
+    } else {
+        samples
+    };
+
+    let mut events = canvases.events.borrow_mut();
+
+    if device_id.kind() == DeviceKind::Touch {
+        let (id, canvas) = match target {
+            Some(target) => target,
+            None => return,
+        };
+        for sample in &samples {
+            let (client_x, client_y) = (sample.client_x() as f64, sample.client_y() as f64);
+            let touch = Touch {
+                device_id,
+                phase,
+                location: canvas_position(&canvas, client_x, client_y),
+                id: ev.pointer_id() as u64,
+                pointer: Some(PointerDetails::from_pointer_event(sample)),
+            };
+            let event = Event::WindowEvent {
+                window_id: id,
+                event: WindowEvent::Touch(touch),
+            };
+            events.push(TimedEvent::new(sample.time_stamp(), event));
+        }
+        return;
+    }
+
+    let target_id = target.as_ref().map(|&(id, _)| id);
+    let hovered = canvases.hovered.replace(target_id);
+    if hovered != target_id {
+        if let Some(window_id) = hovered {
+            let event = WindowEvent::CursorLeft { device_id };
+            let event = Event::WindowEvent { window_id, event };
+            events.push(TimedEvent::new(time, event));
+        }
+        if let Some(window_id) = target_id {
+            let event = WindowEvent::CursorEntered { device_id };
+            let event = Event::WindowEvent { window_id, event };
+            events.push(TimedEvent::new(time, event));
+        }
+    }
+
+    let modifiers = ModifiersState::from_mouse_event(ev);
+    if let Some((window_id, ref canvas)) = target {
+        for sample in &samples {
+            let (client_x, client_y) = (sample.client_x() as f64, sample.client_y() as f64);
+            let event = WindowEvent::CursorMoved {
+                device_id,
+                position: canvas_position(canvas, client_x, client_y),
+                modifiers,
+                pointer: match device_id.kind() {
+                    DeviceKind::Pen => Some(PointerDetails::from_pointer_event(sample)),
+                    _ => None,
+                },
+            };
+            let event = Event::WindowEvent { window_id, event };
+            events.push(TimedEvent::new(sample.time_stamp(), event));
+        }
+
+        // Pressing a second button while one is held only shows up as a move with `button` set.
+        let (button, state) = match phase {
+            TouchPhase::Started => (ev.button(), ElementState::Pressed),
+            TouchPhase::Ended => (ev.button(), ElementState::Released),
+            // The pen left the surface without a pointerup.
+            TouchPhase::Cancelled => (0, ElementState::Released),
+            TouchPhase::Moved if ev.button() >= 0 => {
+                let state = if ev.buttons() & button_mask(ev.button()) != 0 {
+                    ElementState::Pressed
+                } else {
+                    ElementState::Released
+                };
+                (ev.button(), state)
+            }
+            TouchPhase::Moved => (-1, ElementState::Released),
+        };
+        if button >= 0 {
+            let event = WindowEvent::MouseInput {
+                device_id,
+                state,
+                button: MouseButton::from_mouse_button(button),
+                modifiers,
+            };
+            let event = Event::WindowEvent { window_id, event };
+            events.push(TimedEvent::new(time, event));
+        }
+    }
+
+    // Movement keeps being reported while the pointer is locked.
+    if phase == TouchPhase::Moved {
+        let event = Event::DeviceEvent {
+            device_id,
+            event: DeviceEvent::MouseMotion {
+                delta: (ev.movement_x() as _, ev.movement_y() as _),
+            },
+        };
+        events.push(TimedEvent::new(time, event));
+    }
+}
+
+/// The bit of `MouseEvent.buttons` that belongs to a `MouseEvent.button` value.
+fn button_mask(button: i16) -> u16 {
+    match button {
+        // The middle and right buttons are swapped between the two.
+        1 => 4,
+        2 => 2,
+        button if button < 16 => 1 << button,
+        _ => 0,
+    }
+}
+
+impl DeviceId {
+    /// Each pen is told apart by its `pointerId`. Touch contacts all belong to `DeviceId::TOUCH`,
+    /// with the `pointerId` as the finger id.
+    pub fn from_pointer_event(ev: &PointerEvent) -> DeviceId {
+        match ev.pointer_type().as_str() {
+            "pen" => DeviceId::new(DeviceKind::Pen, ev.pointer_id() as u64),
+            "touch" => DeviceId::TOUCH,
+            _ => DeviceId::MOUSE,
+        }
+    }
+}
+
+impl PointerDetails {
+    pub fn from_pointer_event(ev: &PointerEvent) -> PointerDetails {
+        PointerDetails {
+            pressure: ev.pressure(),
+            tilt: (ev.tilt_x() as f32, ev.tilt_y() as f32),
+            twist: ev.twist() as f32,
+            size: (ev.width() as f64, ev.height() as f64),
+        }
+    }
+}
+
+impl ModifiersState {
+    pub fn from_mouse_event(ev: &MouseEvent) -> ModifiersState {
+        ModifiersState {
+            shift: ev.shift_key(),
+            ctrl: ev.ctrl_key(),
+            alt: ev.alt_key(),
+            logo: ev.meta_key(),
+        }
+    }
+}
+
 impl KeyboardInput {
     pub fn from_keyboard_event(ev: &KeyboardEvent, state: ElementState) -> KeyboardInput {
         KeyboardInput {
-            scancode: 0,
+            scancode: scancode_from_code(&ev.code()).unwrap_or(0),
             state,
-            virtual_keycode: VirtualKeyCode::from_key(ev.key()),
+            virtual_keycode: VirtualKeyCode::from_key(ev.key())
+                .map(|key| key.with_location(ev.location())),
             modifiers: ModifiersState {
                 shift: ev.shift_key(),
                 ctrl: ev.ctrl_key(),
                 alt: ev.alt_key(),
-                logo: false,
+                logo: ev.meta_key(),
             },
         }
     }
 }
+
+/// Reads gamepads through the browser Gamepad API (`navigator.getGamepads()`).
+pub struct WebGamepads {
+    navigator: Navigator,
+}
+
+impl WebGamepads {
+    pub fn new(navigator: Navigator) -> WebGamepads {
+        WebGamepads { navigator }
+    }
+}
+
+impl GamepadSource for WebGamepads {
+    fn poll(&mut self, pads: &mut Vec<Option<GamepadState>>) {
+        pads.clear();
+        let gamepads = match self.navigator.get_gamepads() {
+            Ok(gamepads) => gamepads,
+            Err(_) => return,
+        };
+        for slot in gamepads.iter() {
+            let pad = slot
+                .dyn_into::<Gamepad>()
+                .ok()
+                .filter(|pad| pad.connected())
+                .map(|pad| {
+                    // Pads without the standard mapping still get their buttons and axes copied
+                    // by index, which is the best that can be done without a mapping database.
+                    let mut state = GamepadState::new(pad.id());
+                    let buttons: Array = pad.buttons();
+                    for (i, button) in buttons.iter().take(BUTTON_COUNT).enumerate() {
+                        if let Ok(button) = button.dyn_into::<web_sys::GamepadButton>() {
+                            state.buttons[i] = button.pressed();
+                        }
+                    }
+                    let axes: Array = pad.axes();
+                    for (i, axis) in axes.iter().take(4).enumerate() {
+                        state.axes[i] = axis.as_f64().unwrap_or(0.0);
+                    }
+                    // The standard mapping exposes the triggers as analog buttons.
+                    for &(button, axis) in &[
+                        (6, GamepadAxis::LeftTrigger),
+                        (7, GamepadAxis::RightTrigger),
+                    ] {
+                        if let Ok(button) = buttons.get(button).dyn_into::<web_sys::GamepadButton>()
+                        {
+                            state.axes[axis as usize] = button.value();
+                        }
+                    }
+                    state
+                });
+            pads.push(pad);
+        }
+    }
+}
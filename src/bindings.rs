@@ -0,0 +1,842 @@
+//! Named actions and axes bound to keys, buttons and analog axes.
+//!
+//! Games declare what they care about ("jump", "fire", "move_x") and bind it to one or more
+//! inputs. Each frame the bindings are queried against the app's `InputState`:
+//!
+//! ```ignore
+//! let mut map = InputMap::new();
+//! map.bind_action("jump", "Space".parse().unwrap());
+//! map.bind_action("save", "Ctrl+Shift+S".parse().unwrap());
+//! map.bind_axis("move_x", "A/D".parse().unwrap());
+//!
+//! if map.pressed(&app.input, "jump") { /* ... */ }
+//! let dx = map.axis(&app.input, "move_x");
+//! ```
+//!
+//! A map can be written out with `to_string()` and read back with `parse()`, so players can
+//! rebind keys. The format is a small subset of TOML:
+//!
+//! ```toml
+//! [actions]
+//! jump = ["Space", "MouseLeft", "Gamepad:South"]
+//! save = ["Ctrl+Shift+S"]
+//!
+//! [axes]
+//! move_x = ["A/D", "Left/Right", "Gamepad:LeftStickX"]
+//! ```
+//!
+//! `Scan:KeyW` style bindings name a physical key by its scan code. They are not portable
+//! between backends: the web backend and native Linux report Linux evdev codes, which the names
+//! stand for, while native Windows and macOS report codes of their own. Keep `Scan:` bindings to
+//! the setup that saved them, and bind keys by name (`W`) in maps that are shared between
+//! backends. See `events::scancode_from_code`.
+
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use events::*;
+use gamepad::{GamepadAxis, GamepadButton};
+
+/// An input, optionally combined with modifier keys into a chord such as "Ctrl+Shift+S".
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub struct Binding {
+    /// Modifiers that must be held for the binding to trigger.
+    pub modifiers: ModifiersState,
+    pub input: Input,
+}
+
+impl Binding {
+    pub fn new<I: Into<Input>>(input: I) -> Binding {
+        Binding {
+            modifiers: ModifiersState::default(),
+            input: input.into(),
+        }
+    }
+
+    pub fn with_modifiers(mut self, modifiers: ModifiersState) -> Binding {
+        self.modifiers = modifiers;
+        self
+    }
+}
+
+impl<I: Into<Input>> From<I> for Binding {
+    fn from(input: I) -> Binding {
+        Binding::new(input)
+    }
+}
+
+/// Something that drives a named axis.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum AxisBinding {
+    /// Two inputs pushing the axis towards -1 and +1.
+    Buttons {
+        negative: Binding,
+        positive: Binding,
+    },
+    /// An analog axis reported through `AxisMotion` or `DeviceEvent::Motion`.
+    Analog { axis: AxisId, inverted: bool },
+    /// An axis of any connected gamepad.
+    Gamepad { axis: GamepadAxis, inverted: bool },
+}
+
+/// A binding that is used by more than one action or axis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub binding: Binding,
+    /// Names of the actions and axes sharing the binding, in alphabetical order.
+    pub names: Vec<String>,
+}
+
+/// The set of named actions and axes and the inputs bound to them.
+#[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InputMap {
+    actions: BTreeMap<String, Vec<Binding>>,
+    axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl InputMap {
+    pub fn new() -> InputMap {
+        InputMap::default()
+    }
+
+    /// Adds a binding to an action, declaring the action if needed.
+    pub fn bind_action<B: Into<Binding>>(&mut self, action: &str, binding: B) {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        let binding = binding.into();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Adds a binding to an axis, declaring the axis if needed.
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        let bindings = self.axes.entry(axis.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes every binding of an action, keeping the action declared.
+    pub fn clear_action(&mut self, action: &str) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.clear();
+        }
+    }
+
+    /// Removes every binding of an axis, keeping the axis declared.
+    pub fn clear_axis(&mut self, axis: &str) {
+        if let Some(bindings) = self.axes.get_mut(axis) {
+            bindings.clear();
+        }
+    }
+
+    pub fn action_bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map(|b| &b[..]).unwrap_or(&[])
+    }
+
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map(|b| &b[..]).unwrap_or(&[])
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(|name| name.as_str())
+    }
+
+    pub fn axes(&self) -> impl Iterator<Item = &str> {
+        self.axes.keys().map(|name| name.as_str())
+    }
+
+    /// Returns true if any binding of the action went down this frame.
+    pub fn pressed(&self, state: &InputState, action: &str) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|b| self.is_active(state, b) && state.just_pressed(b.input))
+    }
+
+    /// Returns true while any binding of the action is held.
+    pub fn down(&self, state: &InputState, action: &str) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|b| self.is_active(state, b) && state.is_down(b.input))
+    }
+
+    /// Returns true if any binding of the action went up this frame.
+    ///
+    /// Modifiers are not checked here, so releasing the modifiers of a chord before its key
+    /// still counts as a release.
+    pub fn released(&self, state: &InputState, action: &str) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|b| state.just_released(b.input))
+    }
+
+    /// The value of an axis in `-1.0..=1.0`, summing all of its bindings.
+    pub fn axis(&self, state: &InputState, axis: &str) -> f32 {
+        let value: f32 = self
+            .axis_bindings(axis)
+            .iter()
+            .map(|binding| match *binding {
+                AxisBinding::Buttons {
+                    ref negative,
+                    ref positive,
+                } => {
+                    let held = |b: &Binding| self.is_active(state, b) && state.is_down(b.input);
+                    match (held(negative), held(positive)) {
+                        (true, false) => -1.0,
+                        (false, true) => 1.0,
+                        _ => 0.0,
+                    }
+                }
+                AxisBinding::Analog { axis, inverted } => invert(state.axis(axis), inverted),
+                AxisBinding::Gamepad { axis, inverted } => {
+                    invert(state.gamepad_axis(axis), inverted)
+                }
+            })
+            .sum();
+        value.clamp(-1.0, 1.0)
+    }
+
+    /// A binding is active when its modifiers are held, unless another binding on the same
+    /// input asks for a larger set of modifiers that are also held. This way "S" does not fire
+    /// together with "Ctrl+S", while "W" still works with Shift held for sprinting.
+    fn is_active(&self, state: &InputState, binding: &Binding) -> bool {
+        let held = state.modifiers();
+        if !is_subset(binding.modifiers, held) {
+            return false;
+        }
+        !self.all_bindings().any(|other| {
+            other.input == binding.input
+                && other.modifiers != binding.modifiers
+                && is_subset(binding.modifiers, other.modifiers)
+                && is_subset(other.modifiers, held)
+        })
+    }
+
+    fn all_bindings<'a>(&'a self) -> impl Iterator<Item = Binding> + 'a {
+        self.named_bindings().map(|(_, binding)| binding)
+    }
+
+    fn named_bindings<'a>(&'a self) -> impl Iterator<Item = (&'a str, Binding)> + 'a {
+        let actions = self
+            .actions
+            .iter()
+            .flat_map(|(name, bindings)| bindings.iter().map(move |b| (name.as_str(), *b)));
+        let axes = self.axes.iter().flat_map(|(name, bindings)| {
+            bindings.iter().flat_map(move |binding| {
+                let buttons = match *binding {
+                    AxisBinding::Buttons { negative, positive } => vec![negative, positive],
+                    AxisBinding::Analog { .. } | AxisBinding::Gamepad { .. } => vec![],
+                };
+                buttons.into_iter().map(move |b| (name.as_str(), b))
+            })
+        });
+        actions.chain(axes)
+    }
+
+    /// Lists the bindings that trigger more than one action or axis.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut users: Vec<(Binding, Vec<String>)> = Vec::new();
+        for (name, binding) in self.named_bindings() {
+            match users.iter().position(|&(b, _)| b == binding) {
+                Some(i) => {
+                    if !users[i].1.iter().any(|n| n == name) {
+                        users[i].1.push(name.to_string());
+                    }
+                }
+                None => users.push((binding, vec![name.to_string()])),
+            }
+        }
+        users
+            .into_iter()
+            .filter(|(_, names)| names.len() > 1)
+            .map(|(binding, mut names)| {
+                names.sort();
+                Conflict { binding, names }
+            })
+            .collect()
+    }
+}
+
+fn invert(value: f64, inverted: bool) -> f32 {
+    if inverted {
+        -value as f32
+    } else {
+        value as f32
+    }
+}
+
+fn is_subset(a: ModifiersState, b: ModifiersState) -> bool {
+    (!a.shift || b.shift) && (!a.ctrl || b.ctrl) && (!a.alt || b.alt) && (!a.logo || b.logo)
+}
+
+/// Error returned when a binding or an input map fails to parse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// 1-based line of the input map, or 0 when parsing a single binding.
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new<T: Into<String>>(message: T) -> ParseError {
+        ParseError {
+            line: 0,
+            message: message.into(),
+        }
+    }
+
+    fn at_line(mut self, line: usize) -> ParseError {
+        self.line = line;
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "line {}: {}", self.line, self.message)
+        } else {
+            f.write_str(&self.message)
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Input::Key(key) => write!(f, "{:?}", key),
+            Input::ScanCode(code) => match scancode_name(code) {
+                Some(name) => write!(f, "Scan:{}", name),
+                None => write!(f, "Scan:{}", code),
+            },
+            Input::Mouse(MouseButton::Left) => f.write_str("MouseLeft"),
+            Input::Mouse(MouseButton::Right) => f.write_str("MouseRight"),
+            Input::Mouse(MouseButton::Middle) => f.write_str("MouseMiddle"),
+            Input::Mouse(MouseButton::Other(n)) => write!(f, "Mouse{}", n),
+            Input::Gamepad(button) => write!(f, "Gamepad:{:?}", button),
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Input, ParseError> {
+        let unknown = || ParseError::new(format!("unknown input `{}`", s));
+        if let Some(name) = s.strip_prefix("Scan:") {
+            return scancode_from_code(name)
+                .or_else(|| name.parse().ok())
+                .map(Input::ScanCode)
+                .ok_or_else(unknown);
+        }
+        match s {
+            "MouseLeft" => return Ok(Input::Mouse(MouseButton::Left)),
+            "MouseRight" => return Ok(Input::Mouse(MouseButton::Right)),
+            "MouseMiddle" => return Ok(Input::Mouse(MouseButton::Middle)),
+            _ => (),
+        }
+        if let Some(name) = s.strip_prefix("Gamepad:") {
+            return GamepadButton::ALL
+                .iter()
+                .find(|button| format!("{:?}", button) == name)
+                .map(|&button| Input::Gamepad(button))
+                .ok_or_else(unknown);
+        }
+        if let Some(Ok(n)) = s.strip_prefix("Mouse").map(str::parse) {
+            return Ok(Input::Mouse(MouseButton::Other(n)));
+        }
+        VirtualKeyCode::from_name(s)
+            .map(Input::Key)
+            .ok_or_else(unknown)
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.ctrl {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.shift {
+            f.write_str("Shift+")?;
+        }
+        if self.modifiers.alt {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.logo {
+            f.write_str("Logo+")?;
+        }
+        self.input.fmt(f)
+    }
+}
+
+impl FromStr for Binding {
+    type Err = ParseError;
+
+    /// Parses chords such as `"Space"`, `"Ctrl+Shift+S"` or `"Alt+MouseLeft"`.
+    fn from_str(s: &str) -> Result<Binding, ParseError> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let input = parts.pop().unwrap_or("").parse()?;
+        let mut modifiers = ModifiersState::default();
+        for part in parts {
+            let flag = match part {
+                "Ctrl" | "Control" => &mut modifiers.ctrl,
+                "Shift" => &mut modifiers.shift,
+                "Alt" => &mut modifiers.alt,
+                "Logo" | "Meta" | "Cmd" | "Super" => &mut modifiers.logo,
+                _ => return Err(ParseError::new(format!("unknown modifier `{}`", part))),
+            };
+            *flag = true;
+        }
+        Ok(Binding { modifiers, input })
+    }
+}
+
+impl fmt::Display for AxisBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AxisBinding::Buttons { negative, positive } => write!(f, "{}/{}", negative, positive),
+            AxisBinding::Analog { axis, inverted } => {
+                write!(f, "{}Axis{}", if inverted { "-" } else { "" }, axis)
+            }
+            AxisBinding::Gamepad { axis, inverted } => {
+                write!(f, "{}Gamepad:{:?}", if inverted { "-" } else { "" }, axis)
+            }
+        }
+    }
+}
+
+impl FromStr for AxisBinding {
+    type Err = ParseError;
+
+    /// Parses `"A/D"` (negative/positive buttons), `"Axis0"`, `"Gamepad:LeftStickX"`, or either
+    /// of the latter two prefixed with `-` to invert them.
+    fn from_str(s: &str) -> Result<AxisBinding, ParseError> {
+        if let Some(slash) = s.find('/') {
+            return Ok(AxisBinding::Buttons {
+                negative: s[..slash].trim().parse()?,
+                positive: s[slash + 1..].trim().parse()?,
+            });
+        }
+        let (inverted, rest) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        if let Some(Ok(axis)) = rest.strip_prefix("Axis").map(str::parse) {
+            return Ok(AxisBinding::Analog { axis, inverted });
+        }
+        if let Some(name) = rest.strip_prefix("Gamepad:") {
+            if let Some(&axis) = GamepadAxis::ALL
+                .iter()
+                .find(|axis| format!("{:?}", axis) == name)
+            {
+                return Ok(AxisBinding::Gamepad { axis, inverted });
+            }
+        }
+        Err(ParseError::new(format!("unknown axis binding `{}`", s)))
+    }
+}
+
+impl fmt::Display for InputMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "[actions]")?;
+        for (name, bindings) in &self.actions {
+            write_entry(f, name, bindings)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "[axes]")?;
+        for (name, bindings) in &self.axes {
+            write_entry(f, name, bindings)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_entry<T: fmt::Display>(f: &mut fmt::Formatter, name: &str, items: &[T]) -> fmt::Result {
+    let bare = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        f.write_str(name)?;
+    } else {
+        write_string(f, name)?;
+    }
+    f.write_str(" = [")?;
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write_string(f, &item.to_string())?;
+    }
+    writeln!(f, "]")
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl FromStr for InputMap {
+    type Err = ParseError;
+
+    /// Parses the TOML subset written by `Display`: `[actions]` and `[axes]` tables whose
+    /// entries are single-line arrays of strings.
+    fn from_str(s: &str) -> Result<InputMap, ParseError> {
+        enum Section {
+            None,
+            Actions,
+            Axes,
+        }
+
+        let mut map = InputMap::new();
+        let mut section = Section::None;
+        for (i, line) in s.lines().enumerate() {
+            let at_line = |e: ParseError| e.at_line(i + 1);
+            let mut tokens = Tokens::new(line);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') {
+                section = match strip_comment(line) {
+                    "[actions]" => Section::Actions,
+                    "[axes]" => Section::Axes,
+                    other => {
+                        return Err(at_line(ParseError::new(format!(
+                            "unknown table `{}`",
+                            other
+                        ))))
+                    }
+                };
+                continue;
+            }
+
+            let name = tokens.key().map_err(at_line)?;
+            let values = tokens.string_array().map_err(at_line)?;
+            match section {
+                Section::Actions => {
+                    map.actions.entry(name.clone()).or_default();
+                    for value in values {
+                        map.bind_action(&name, value.parse::<Binding>().map_err(at_line)?);
+                    }
+                }
+                Section::Axes => {
+                    map.axes.entry(name.clone()).or_default();
+                    for value in values {
+                        map.bind_axis(&name, value.parse().map_err(at_line)?);
+                    }
+                }
+                Section::None => {
+                    return Err(at_line(ParseError::new(
+                        "entry outside of [actions] or [axes]",
+                    )))
+                }
+            }
+        }
+        Ok(map)
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(i) => line[..i].trim(),
+        None => line,
+    }
+}
+
+/// Minimal tokenizer for `key = ["value", ...]` lines.
+struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(line: &'a str) -> Tokens<'a> {
+        Tokens { rest: line }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.rest.starts_with(c) {
+            self.rest = &self.rest[c.len_utf8()..];
+            Ok(())
+        } else {
+            Err(ParseError::new(format!("expected `{}`", c)))
+        }
+    }
+
+    fn key(&mut self) -> Result<String, ParseError> {
+        self.skip_whitespace();
+        let key = if self.rest.starts_with('"') {
+            self.string()?
+        } else {
+            let end = self
+                .rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(self.rest.len());
+            if end == 0 {
+                return Err(ParseError::new("expected a name"));
+            }
+            let key = self.rest[..end].to_string();
+            self.rest = &self.rest[end..];
+            key
+        };
+        self.expect('=')?;
+        Ok(key)
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let mut value = String::new();
+        let mut chars = self.rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[i + 1..];
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, '"')) => value.push('"'),
+                    Some((_, '\\')) => value.push('\\'),
+                    _ => return Err(ParseError::new("unsupported escape sequence")),
+                },
+                c => value.push(c),
+            }
+        }
+        Err(ParseError::new("unterminated string"))
+    }
+
+    fn string_array(&mut self) -> Result<Vec<String>, ParseError> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.rest.starts_with(']') {
+                self.rest = &self.rest[1..];
+                break;
+            }
+            values.push(self.string()?);
+            self.skip_whitespace();
+            if self.rest.starts_with(',') {
+                self.rest = &self.rest[1..];
+            } else {
+                self.expect(']')?;
+                break;
+            }
+        }
+        self.skip_whitespace();
+        if !self.rest.is_empty() && !self.rest.starts_with('#') {
+            return Err(ParseError::new("unexpected characters after the array"));
+        }
+        Ok(values)
+    }
+}
+
+// Bindings are stored in their text form, e.g. "Ctrl+Shift+S" or "A/D", so keymaps stay
+// readable in any serde format.
+#[cfg(feature = "serde")]
+mod serialization {
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{AxisBinding, Binding};
+
+    fn serialize_str<T: Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    fn deserialize_str<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(D::Error::custom)
+    }
+
+    impl Serialize for Binding {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize_str(self, serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Binding {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Binding, D::Error> {
+            deserialize_str(deserializer)
+        }
+    }
+
+    impl Serialize for AxisBinding {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize_str(self, serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for AxisBinding {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<AxisBinding, D::Error> {
+            deserialize_str(deserializer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modifiers(ctrl: bool, shift: bool, alt: bool, logo: bool) -> ModifiersState {
+        ModifiersState {
+            shift,
+            ctrl,
+            alt,
+            logo,
+        }
+    }
+
+    #[test]
+    fn chords_round_trip() {
+        let save: Binding = "Ctrl+Shift+S".parse().unwrap();
+        assert_eq!(save.modifiers, modifiers(true, true, false, false));
+        assert_eq!(save.input, Input::Key(VirtualKeyCode::S));
+
+        for chord in &[
+            "Space",
+            "Ctrl+Shift+S",
+            "Alt+MouseLeft",
+            "Logo+Mouse4",
+            "Key1",
+        ] {
+            let binding: Binding = chord.parse().unwrap();
+            assert_eq!(binding.to_string(), *chord);
+        }
+        // Aliases and spacing are normalized.
+        let binding: Binding = "Control + Meta + A".parse().unwrap();
+        assert_eq!(binding.to_string(), "Ctrl+Logo+A");
+    }
+
+    #[test]
+    fn key_names() {
+        assert_eq!(
+            VirtualKeyCode::from_name("Key1"),
+            Some(VirtualKeyCode::Key1)
+        );
+        assert_eq!(VirtualKeyCode::from_name("Yen"), Some(VirtualKeyCode::Yen));
+        assert_eq!(
+            VirtualKeyCode::from_name("NavigateBackward"),
+            Some(VirtualKeyCode::NavigateBackward)
+        );
+        assert_eq!(VirtualKeyCode::from_name("space"), None);
+        assert_eq!(VirtualKeyCode::from_name(""), None);
+    }
+
+    #[test]
+    fn scan_codes_round_trip() {
+        let named: Binding = "Shift+Scan:KeyW".parse().unwrap();
+        assert_eq!(
+            named.input,
+            Input::ScanCode(scancode_from_code("KeyW").unwrap())
+        );
+        assert_eq!(named.to_string(), "Shift+Scan:KeyW");
+
+        let numbered: Input = "Scan:65000".parse().unwrap();
+        assert_eq!(numbered, Input::ScanCode(65000));
+        assert_eq!(numbered.to_string(), "Scan:65000");
+
+        assert!("Scan:Nope".parse::<Input>().is_err());
+    }
+
+    #[test]
+    fn axis_bindings_round_trip() {
+        for text in &[
+            "A/D",
+            "Ctrl+Left/Ctrl+Right",
+            "Axis3",
+            "-Axis0",
+            "-Gamepad:LeftStickY",
+        ] {
+            let binding: AxisBinding = text.parse().unwrap();
+            assert_eq!(binding.to_string(), *text);
+        }
+    }
+
+    #[test]
+    fn map_round_trips() {
+        let mut map = InputMap::new();
+        map.bind_action("jump", "Space".parse::<Binding>().unwrap());
+        map.bind_action("jump", "Gamepad:South".parse::<Binding>().unwrap());
+        map.bind_action("open door", "Scan:KeyE".parse::<Binding>().unwrap());
+        map.bind_action("say \"hi\\\"", "Ctrl+Shift+H".parse::<Binding>().unwrap());
+        map.bind_axis("move_x", "A/D".parse().unwrap());
+        map.bind_axis("move_x", "Gamepad:LeftStickX".parse().unwrap());
+        // Declared, but without bindings.
+        map.bind_axis("zoom", "Axis2".parse().unwrap());
+        map.clear_axis("zoom");
+
+        let text = map.to_string();
+        assert!(text.contains("\"open door\" = [\"Scan:KeyE\"]"));
+        assert!(text.contains("\"say \\\"hi\\\\\\\"\" = [\"Ctrl+Shift+H\"]"));
+        assert_eq!(text.parse::<InputMap>().unwrap(), map);
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        let map: InputMap = "
+            # Key bindings
+            [actions] # buttons
+            jump = [\"Space\", \"MouseLeft\"] # and the mouse
+            \"fire # 2\" = [\"Ctrl+F\"]
+
+            [axes]
+            # move_x = [\"A/D\"]
+            "
+        .parse()
+        .unwrap();
+        assert_eq!(map.action_bindings("jump").len(), 2);
+        assert_eq!(map.action_bindings("fire # 2").len(), 1);
+        assert_eq!(map.axes().count(), 0);
+    }
+
+    #[test]
+    fn malformed_input_is_reported_with_its_line() {
+        let error = |text: &str| text.parse::<InputMap>().unwrap_err();
+
+        assert_eq!(error("jump = [\"Space\"]").line, 1);
+        assert_eq!(error("[actions]\n[keys]").line, 2);
+        assert_eq!(error("[actions]\njump [\"Space\"]").message, "expected `=`");
+        assert_eq!(
+            error("[actions]\njump = [\"Space]").message,
+            "unterminated string"
+        );
+        assert_eq!(error("[actions]\njump = [\"Space\"] x").line, 2);
+        assert_eq!(error("[actions]\njump = \"Space\"").message, "expected `[`");
+        assert_eq!(error("[actions]\njump = [\"\\n\"]").line, 2);
+        assert_eq!(error("[actions]\n\njump = [\"Spcae\"]").line, 3);
+        assert_eq!(error("[axes]\nmove = [\"Space\"]").line, 2);
+
+        assert!("Hyper+A".parse::<Binding>().is_err());
+        assert!("Ctrl+".parse::<Binding>().is_err());
+        assert!("Gamepad:Nope".parse::<Input>().is_err());
+        assert!("A/".parse::<AxisBinding>().is_err());
+    }
+}
@@ -1,13 +1,103 @@
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Copy)]
-pub struct WindowId;
+use gamepad::{GamepadAxis, GamepadButton};
 
-#[derive(Debug, Clone, Copy)]
-pub struct DeviceId;
+/// Identifies a window, or a canvas on the web.
+///
+/// The window created with the app is `WindowId::PRIMARY`; `App::create_window` hands out ids
+/// for the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WindowId(pub u32);
+
+impl WindowId {
+    pub const PRIMARY: WindowId = WindowId(0);
+}
+
+/// What kind of device an input comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DeviceKind {
+    Mouse,
+    Pen,
+    Touch,
+    Keyboard,
+    Gamepad,
+    /// A device the platform does not classify, e.g. a joystick reporting raw axes.
+    Other,
+}
+
+/// Identifies an input device, so that e.g. two gamepads or a mouse and a drawing tablet can
+/// be told apart.
+///
+/// The id is only unique among devices of the same kind. Gamepads use their slot in
+/// `Gamepads`. On the web, pens use their DOM `pointerId`, while the mouse, the touch screen and
+/// the keyboard each count as a single device. On native, the id is derived from the
+/// platform's device id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceId {
+    kind: DeviceKind,
+    id: u64,
+}
+
+impl DeviceId {
+    /// The mouse of a backend that cannot tell mice apart. Its id is the `pointerId` browsers
+    /// give the mouse.
+    pub const MOUSE: DeviceId = DeviceId::new(DeviceKind::Mouse, 1);
+    /// The touch screen of a backend that cannot tell touch screens apart.
+    pub const TOUCH: DeviceId = DeviceId::new(DeviceKind::Touch, 0);
+    /// The keyboard of a backend that cannot tell keyboards apart.
+    pub const KEYBOARD: DeviceId = DeviceId::new(DeviceKind::Keyboard, 0);
+
+    pub const fn new(kind: DeviceKind, id: u64) -> DeviceId {
+        DeviceId { kind, id }
+    }
+
+    /// The gamepad in slot `index` of `Gamepads`.
+    pub const fn gamepad(index: usize) -> DeviceId {
+        DeviceId::new(DeviceKind::Gamepad, index as u64)
+    }
+
+    pub fn kind(&self) -> DeviceKind {
+        self.kind
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The slot of a gamepad in `Gamepads`, or `None` for other devices.
+    pub fn gamepad_index(&self) -> Option<usize> {
+        match self.kind {
+            DeviceKind::Gamepad => Some(self.id as usize),
+            _ => None,
+        }
+    }
+
+    /// A name to show to players, like "Gamepad 2" or "Pen 3". Numbers start at 1.
+    ///
+    /// `GamepadState::name` has the name the driver reports for a gamepad.
+    pub fn name(&self) -> String {
+        let kind = match self.kind {
+            DeviceKind::Mouse => "Mouse",
+            DeviceKind::Pen => "Pen",
+            DeviceKind::Touch => "Touch screen",
+            DeviceKind::Keyboard => "Keyboard",
+            DeviceKind::Gamepad => "Gamepad",
+            DeviceKind::Other => "Input device",
+        };
+        match self.kind {
+            DeviceKind::Gamepad | DeviceKind::Pen => format!("{} {}", kind, self.id + 1),
+            _ => kind.to_string(),
+        }
+    }
+}
 
 /// Describes a generic event.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Event {
     WindowEvent {
         window_id: WindowId,
@@ -25,8 +115,38 @@ pub enum Event {
     Suspended(bool),
 }
 
+impl AsRef<Event> for Event {
+    fn as_ref(&self) -> &Event {
+        self
+    }
+}
+
+/// An event and the time it happened, in milliseconds.
+///
+/// Times use the clock of `App::frame_time`: on the web the DOM `timeStamp` of the event, which
+/// shares its clock with animation frame times, and on native the time the event was polled.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimedEvent {
+    pub time: f64,
+    pub event: Event,
+}
+
+impl TimedEvent {
+    pub fn new(time: f64, event: Event) -> TimedEvent {
+        TimedEvent { time, event }
+    }
+}
+
+impl AsRef<Event> for TimedEvent {
+    fn as_ref(&self) -> &Event {
+        &self.event
+    }
+}
+
 /// Describes an event from a `Window`.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum WindowEvent {
     /// The size of the window has changed.
     Resized(u32, u32),
@@ -46,6 +166,20 @@ pub enum WindowEvent {
     /// A file was hovered, but has exited the window.
     HoveredFileCancelled,
 
+    /// The contents of a file dropped into the window.
+    ///
+    /// Delivered after `DroppedFile` on every backend, including the web where dropped files
+    /// have no path. Contents are read asynchronously in the browser, so they may arrive a few
+    /// frames later.
+    DroppedFileContents(FileContents),
+
+    /// A dropped file could not be read, so no `DroppedFileContents` follows its `DroppedFile`.
+    DroppedFileFailed {
+        /// File name, without the directory.
+        name: String,
+        error: String,
+    },
+
     /// The window received a unicode character.
     ReceivedCharacter(char),
 
@@ -67,8 +201,14 @@ pub enum WindowEvent {
         /// (x,y) coords in pixels relative to the top-left corner of the window. Because the range of this data is
         /// limited by the display area and it may have been transformed by the OS to implement effects such as cursor
         /// acceleration, it should not be used to implement non-cursor-like interactions such as 3D camera control.
+        ///
+        /// On the web the window is the canvas and pixels are those of its backing store.
+        /// `App::to_logical` converts to CSS pixels and `Viewport::to_ndc` to normalized device
+        /// coordinates.
         position: (f64, f64),
         modifiers: ModifiersState,
+        /// Pressure, tilt and size of a pen. `None` for a mouse.
+        pointer: Option<PointerDetails>,
     },
 
     /// The cursor has entered the window.
@@ -77,6 +217,15 @@ pub enum WindowEvent {
     /// The cursor has left the window.
     CursorLeft { device_id: DeviceId },
 
+    /// The cursor grab requested with `App::set_cursor_grab` was applied or released.
+    ///
+    /// The parameter is true if the cursor is now grabbed. Browsers can also release the grab
+    /// on their own, e.g. when the user presses Escape.
+    CursorGrabChanged(bool),
+
+    /// The cursor grab requested with `App::set_cursor_grab` could not be applied.
+    CursorGrabFailed,
+
     /// A mouse wheel movement or touchpad scroll occurred.
     MouseWheel {
         device_id: DeviceId,
@@ -125,6 +274,42 @@ pub enum WindowEvent {
     /// * A user changes the desktop scaling value (e.g. in Control Panel on Windows).
     /// * A user moves the application window to a display with a different DPI.
     HiDPIFactorChanged(f32),
+
+    /// The input method editor (IME) changed state.
+    ///
+    /// Only delivered while text input is enabled with `App::set_text_input_enabled`.
+    Ime(Ime),
+}
+
+/// A file that was dropped into the window, read into memory.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FileContents {
+    /// File name, without any directories.
+    pub name: String,
+    /// MIME type, e.g. `"image/png"`, or an empty string if it is unknown.
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+/// Describes a change in the input method editor used to compose text, e.g. for CJK languages.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Ime {
+    /// Text input was enabled; `Preedit` and `Commit` events may follow.
+    Enabled,
+
+    /// The text being composed changed.
+    ///
+    /// The range is the byte range of the cursor or selection within the text, if any. An
+    /// empty string means the composition was cleared.
+    Preedit(String, Option<(usize, usize)>),
+
+    /// The composition was confirmed and the text should be inserted.
+    Commit(String),
+
+    /// Text input was disabled.
+    Disabled,
 }
 
 /// Represents raw hardware events that are not associated with any particular window.
@@ -136,6 +321,7 @@ pub enum WindowEvent {
 ///
 /// Note that these events are delivered regardless of input focus.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum DeviceEvent {
     Added,
     Removed,
@@ -175,6 +361,7 @@ pub enum DeviceEvent {
 
 /// Describes a keyboard input event.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct KeyboardInput {
     /// Identifies the physical key pressed
     ///
@@ -200,6 +387,7 @@ pub struct KeyboardInput {
 
 /// Describes touch-screen input state.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TouchPhase {
     Started,
     Moved,
@@ -223,17 +411,243 @@ pub enum TouchPhase {
 ///
 /// Touch may be cancelled if for example window lost focus.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Touch {
     pub device_id: DeviceId,
     pub phase: TouchPhase,
     pub location: (f64, f64),
     /// unique identifier of a finger.
     pub id: u64,
+    /// Pressure and contact size of the finger, if the backend reports them.
+    pub pointer: Option<PointerDetails>,
+}
+
+/// What a pen or touch screen reports beyond the position of a contact.
+///
+/// Only the web backend reports these, from DOM Pointer Events. Values the hardware does not
+/// support are at their defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PointerDetails {
+    /// Normalized pressure from 0 to 1. Hardware without pressure sensing reports 0.5 while in
+    /// contact and 0 otherwise.
+    pub pressure: f32,
+    /// Angle of the pen to the Y-Z plane and to the X-Z plane, in degrees from -90 to 90.
+    pub tilt: (f32, f32),
+    /// Clockwise rotation of the pen around its own axis, in degrees from 0 to 359.
+    pub twist: f32,
+    /// Width and height of the contact area, in CSS pixels.
+    pub size: (f64, f64),
 }
 
 /// Hardware-dependent keyboard scan code.
+///
+/// The web backend reports Linux evdev key codes on every platform, see `scancode_from_code`.
+/// The native backend reports whatever the platform does.
 pub type ScanCode = u32;
 
+/// Physical key names from the DOM `KeyboardEvent.code` specification, paired with their Linux
+/// evdev key codes.
+///
+/// This is a Linux-only table. The native backend reports evdev codes on Linux only. Windows
+/// scan codes (PC set 1) coincide with them for the main block of the keyboard but not for the
+/// keys that set 1 marks as extended, such as the arrows and the right-hand modifiers. macOS key
+/// codes do not match them at all.
+static SCANCODES: &[(&str, ScanCode)] = &[
+    ("Escape", 1),
+    ("Digit1", 2),
+    ("Digit2", 3),
+    ("Digit3", 4),
+    ("Digit4", 5),
+    ("Digit5", 6),
+    ("Digit6", 7),
+    ("Digit7", 8),
+    ("Digit8", 9),
+    ("Digit9", 10),
+    ("Digit0", 11),
+    ("Minus", 12),
+    ("Equal", 13),
+    ("Backspace", 14),
+    ("Tab", 15),
+    ("KeyQ", 16),
+    ("KeyW", 17),
+    ("KeyE", 18),
+    ("KeyR", 19),
+    ("KeyT", 20),
+    ("KeyY", 21),
+    ("KeyU", 22),
+    ("KeyI", 23),
+    ("KeyO", 24),
+    ("KeyP", 25),
+    ("BracketLeft", 26),
+    ("BracketRight", 27),
+    ("Enter", 28),
+    ("ControlLeft", 29),
+    ("KeyA", 30),
+    ("KeyS", 31),
+    ("KeyD", 32),
+    ("KeyF", 33),
+    ("KeyG", 34),
+    ("KeyH", 35),
+    ("KeyJ", 36),
+    ("KeyK", 37),
+    ("KeyL", 38),
+    ("Semicolon", 39),
+    ("Quote", 40),
+    ("Backquote", 41),
+    ("ShiftLeft", 42),
+    ("Backslash", 43),
+    ("KeyZ", 44),
+    ("KeyX", 45),
+    ("KeyC", 46),
+    ("KeyV", 47),
+    ("KeyB", 48),
+    ("KeyN", 49),
+    ("KeyM", 50),
+    ("Comma", 51),
+    ("Period", 52),
+    ("Slash", 53),
+    ("ShiftRight", 54),
+    ("NumpadMultiply", 55),
+    ("AltLeft", 56),
+    ("Space", 57),
+    ("CapsLock", 58),
+    ("F1", 59),
+    ("F2", 60),
+    ("F3", 61),
+    ("F4", 62),
+    ("F5", 63),
+    ("F6", 64),
+    ("F7", 65),
+    ("F8", 66),
+    ("F9", 67),
+    ("F10", 68),
+    ("NumLock", 69),
+    ("ScrollLock", 70),
+    ("Numpad7", 71),
+    ("Numpad8", 72),
+    ("Numpad9", 73),
+    ("NumpadSubtract", 74),
+    ("Numpad4", 75),
+    ("Numpad5", 76),
+    ("Numpad6", 77),
+    ("NumpadAdd", 78),
+    ("Numpad1", 79),
+    ("Numpad2", 80),
+    ("Numpad3", 81),
+    ("Numpad0", 82),
+    ("NumpadDecimal", 83),
+    ("IntlBackslash", 86),
+    ("F11", 87),
+    ("F12", 88),
+    ("IntlRo", 89),
+    ("Convert", 92),
+    ("KanaMode", 93),
+    ("NonConvert", 94),
+    ("NumpadEnter", 96),
+    ("ControlRight", 97),
+    ("NumpadDivide", 98),
+    ("PrintScreen", 99),
+    ("AltRight", 100),
+    ("Home", 102),
+    ("ArrowUp", 103),
+    ("PageUp", 104),
+    ("ArrowLeft", 105),
+    ("ArrowRight", 106),
+    ("End", 107),
+    ("ArrowDown", 108),
+    ("PageDown", 109),
+    ("Insert", 110),
+    ("Delete", 111),
+    ("AudioVolumeMute", 113),
+    ("AudioVolumeDown", 114),
+    ("AudioVolumeUp", 115),
+    ("Power", 116),
+    ("NumpadEqual", 117),
+    ("Pause", 119),
+    ("NumpadComma", 121),
+    ("Lang1", 122),
+    ("Lang2", 123),
+    ("IntlYen", 124),
+    ("MetaLeft", 125),
+    ("MetaRight", 126),
+    ("ContextMenu", 127),
+    ("BrowserStop", 128),
+    ("Again", 129),
+    ("Props", 130),
+    ("Undo", 131),
+    ("Select", 132),
+    ("Copy", 133),
+    ("Open", 134),
+    ("Paste", 135),
+    ("Find", 136),
+    ("Cut", 137),
+    ("Help", 138),
+    ("LaunchApp2", 140),
+    ("Sleep", 142),
+    ("WakeUp", 143),
+    ("LaunchMail", 155),
+    ("BrowserFavorites", 156),
+    ("LaunchApp1", 157),
+    ("BrowserBack", 158),
+    ("BrowserForward", 159),
+    ("Eject", 161),
+    ("MediaTrackNext", 163),
+    ("MediaPlayPause", 164),
+    ("MediaTrackPrevious", 165),
+    ("MediaStop", 166),
+    ("BrowserHome", 172),
+    ("BrowserRefresh", 173),
+    ("F13", 183),
+    ("F14", 184),
+    ("F15", 185),
+    ("F16", 186),
+    ("F17", 187),
+    ("F18", 188),
+    ("F19", 189),
+    ("F20", 190),
+    ("F21", 191),
+    ("F22", 192),
+    ("F23", 193),
+    ("F24", 194),
+    ("BrowserSearch", 217),
+    ("MediaSelect", 226),
+];
+
+/// Translates a DOM `KeyboardEvent.code` value into the Linux evdev key code of that physical
+/// key.
+///
+/// Returns `None` for codes that have no scan code equivalent, such as `"Unidentified"`.
+///
+/// The web backend uses these codes whatever the platform, so they match native scan codes on
+/// Linux only.
+pub fn scancode_from_code(code: &str) -> Option<ScanCode> {
+    let code = match code {
+        // Names used by older browsers before the specification settled.
+        "OSLeft" => "MetaLeft",
+        "OSRight" => "MetaRight",
+        "VolumeMute" => "AudioVolumeMute",
+        "VolumeDown" => "AudioVolumeDown",
+        "VolumeUp" => "AudioVolumeUp",
+        code => code,
+    };
+    SCANCODES
+        .iter()
+        .find(|&&(name, _)| name == code)
+        .map(|&(_, scancode)| scancode)
+}
+
+/// Returns the physical key name of a Linux evdev key code, using the DOM `KeyboardEvent.code`
+/// naming (`"KeyW"`, `"Digit1"`, `"NumpadEnter"`, ...).
+///
+/// Native scan codes of other platforms may get the wrong name or none.
+pub fn scancode_name(scancode: ScanCode) -> Option<&'static str> {
+    SCANCODES
+        .iter()
+        .find(|&&(_, code)| code == scancode)
+        .map(|&(name, _)| name)
+}
+
 /// Identifier for a specific analog axis on some device.
 pub type AxisId = u32;
 
@@ -242,6 +656,7 @@ pub type ButtonId = u32;
 
 /// Describes the input state of a key.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ElementState {
     Pressed,
     Released,
@@ -249,6 +664,7 @@ pub enum ElementState {
 
 /// Describes a button of a mouse controller.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum MouseButton {
     Left,
     Right,
@@ -258,6 +674,7 @@ pub enum MouseButton {
 
 /// Describes a difference in the mouse scroll wheel state.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum MouseScrollDelta {
     /// Amount in lines or rows to scroll in the horizontal
     /// and vertical directions.
@@ -274,8 +691,35 @@ pub enum MouseScrollDelta {
     PixelDelta(f32, f32),
 }
 
+/// Defines `VirtualKeyCode` along with `from_name`, which needs every variant name.
+macro_rules! virtual_key_codes {
+    (
+        $(#[$attr:meta])*
+        pub enum VirtualKeyCode {
+            $($(#[$variant_attr:meta])* $variant:ident,)*
+        }
+    ) => {
+        $(#[$attr])*
+        pub enum VirtualKeyCode {
+            $($(#[$variant_attr])* $variant,)*
+        }
+
+        impl VirtualKeyCode {
+            /// Looks a keycode up by its variant name, e.g. `"Space"` or `"Key1"`.
+            pub fn from_name(name: &str) -> Option<VirtualKeyCode> {
+                match name {
+                    $(stringify!($variant) => Some(VirtualKeyCode::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+virtual_key_codes! {
 /// Symbolic name for a keyboard key.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(u32)]
 pub enum VirtualKeyCode {
     /// The '1' key over the letters.
@@ -459,27 +903,204 @@ pub enum VirtualKeyCode {
     WebStop,
     Yen,
 }
+}
 
 impl VirtualKeyCode {
+    /// Translates a DOM `KeyboardEvent.key` value into a `VirtualKeyCode`.
+    ///
+    /// Letters are matched case-insensitively. Keys that have no `VirtualKeyCode` equivalent
+    /// return `None`, and so does `"Unidentified"`, which browsers report for unknown keys.
+    /// Modifier keys map to their left-hand variant, since `key` alone does not tell the two
+    /// sides apart.
     pub fn from_key(val: String) -> Option<VirtualKeyCode> {
+        use self::VirtualKeyCode::*;
+
         let key = val.as_str();
+        let mut chars = key.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if c.is_ascii_alphabetic() {
+                return Some(match c.to_ascii_lowercase() {
+                    'a' => A,
+                    'b' => B,
+                    'c' => C,
+                    'd' => D,
+                    'e' => E,
+                    'f' => F,
+                    'g' => G,
+                    'h' => H,
+                    'i' => I,
+                    'j' => J,
+                    'k' => K,
+                    'l' => L,
+                    'm' => M,
+                    'n' => N,
+                    'o' => O,
+                    'p' => P,
+                    'q' => Q,
+                    'r' => R,
+                    's' => S,
+                    't' => T,
+                    'u' => U,
+                    'v' => V,
+                    'w' => W,
+                    'x' => X,
+                    'y' => Y,
+                    _ => Z,
+                });
+            }
+        }
+
         Some(match key {
-            "a" => VirtualKeyCode::A,
-            "ArrowDown" => VirtualKeyCode::Down,
-            "ArrowUp" => VirtualKeyCode::Up,
-            "ArrowLeft" => VirtualKeyCode::Left,
-            "ArrowRight" => VirtualKeyCode::Right,
-            "" => VirtualKeyCode::Space,
-            "Enter" => VirtualKeyCode::Return,
-            _ => VirtualKeyCode::Escape,
+            "1" => Key1,
+            "2" => Key2,
+            "3" => Key3,
+            "4" => Key4,
+            "5" => Key5,
+            "6" => Key6,
+            "7" => Key7,
+            "8" => Key8,
+            "9" => Key9,
+            "0" => Key0,
+
+            "Escape" | "Esc" => Escape,
+
+            "F1" => F1,
+            "F2" => F2,
+            "F3" => F3,
+            "F4" => F4,
+            "F5" => F5,
+            "F6" => F6,
+            "F7" => F7,
+            "F8" => F8,
+            "F9" => F9,
+            "F10" => F10,
+            "F11" => F11,
+            "F12" => F12,
+            "F13" => F13,
+            "F14" => F14,
+            "F15" => F15,
+
+            "PrintScreen" => Snapshot,
+            "ScrollLock" | "Scroll" => Scroll,
+            "Pause" => Pause,
+
+            "Insert" => Insert,
+            "Home" => Home,
+            "Delete" | "Del" => Delete,
+            "End" => End,
+            "PageDown" => PageDown,
+            "PageUp" => PageUp,
+
+            "ArrowLeft" | "Left" => Left,
+            "ArrowUp" | "Up" => Up,
+            "ArrowRight" | "Right" => Right,
+            "ArrowDown" | "Down" => Down,
+
+            "Backspace" => Back,
+            "Enter" => Return,
+            " " | "Spacebar" => Space,
+
+            "Compose" => Compose,
+
+            "NumLock" => Numlock,
+
+            "+" => Add,
+            "'" => Apostrophe,
+            "ContextMenu" | "Apps" => Apps,
+            "@" => At,
+            "\\" => Backslash,
+            "LaunchCalculator" | "LaunchApplication2" => Calculator,
+            "CapsLock" => Capital,
+            ":" => Colon,
+            "," => Comma,
+            "Convert" => Convert,
+            "Decimal" => Decimal,
+            "Divide" => Divide,
+            "=" => Equals,
+            "`" => Grave,
+            "KanaMode" => Kana,
+            "KanjiMode" => Kanji,
+            "Alt" => LAlt,
+            "[" => LBracket,
+            "Control" => LControl,
+            "Shift" => LShift,
+            "Meta" | "OS" | "Win" => LWin,
+            "LaunchMail" => Mail,
+            "LaunchMediaPlayer" | "MediaSelect" => MediaSelect,
+            "MediaStop" => MediaStop,
+            "-" => Minus,
+            "*" | "Multiply" => Multiply,
+            "AudioVolumeMute" | "VolumeMute" => Mute,
+            "LaunchMyComputer" | "LaunchApplication1" => MyComputer,
+            "MediaTrackNext" | "MediaNextTrack" => NextTrack,
+            "NonConvert" => NoConvert,
+            "." => Period,
+            "MediaPlayPause" => PlayPause,
+            "Power" => Power,
+            "MediaTrackPrevious" | "MediaPreviousTrack" => PrevTrack,
+            "AltGraph" => RAlt,
+            "]" => RBracket,
+            ";" => Semicolon,
+            "/" => Slash,
+            "Standby" | "Sleep" => Sleep,
+            "Cancel" => Stop,
+            "Subtract" => Subtract,
+            "Tab" => Tab,
+            "_" => Underline,
+            "AudioVolumeDown" | "VolumeDown" => VolumeDown,
+            "AudioVolumeUp" | "VolumeUp" => VolumeUp,
+            "WakeUp" => Wake,
+            "BrowserBack" => WebBack,
+            "BrowserFavorites" => WebFavorites,
+            "BrowserForward" => WebForward,
+            "BrowserHome" => WebHome,
+            "BrowserRefresh" => WebRefresh,
+            "BrowserSearch" => WebSearch,
+            "BrowserStop" => WebStop,
+            "\u{a5}" => Yen,
+            _ => return None,
         })
     }
+
+    /// Refines a keycode from `KeyboardEvent.key` with `KeyboardEvent.location`, which tells
+    /// right-hand modifiers and numpad keys apart from their main-block counterparts.
+    pub fn with_location(self, location: u32) -> VirtualKeyCode {
+        use self::VirtualKeyCode::*;
+
+        // `KeyboardEvent.DOM_KEY_LOCATION_RIGHT` and `DOM_KEY_LOCATION_NUMPAD`.
+        const RIGHT: u32 = 2;
+        const NUMPAD: u32 = 3;
+        match (location, self) {
+            (RIGHT, LAlt) => RAlt,
+            (RIGHT, LControl) => RControl,
+            (RIGHT, LShift) => RShift,
+            (RIGHT, LWin) => RWin,
+            (NUMPAD, Key0) => Numpad0,
+            (NUMPAD, Key1) => Numpad1,
+            (NUMPAD, Key2) => Numpad2,
+            (NUMPAD, Key3) => Numpad3,
+            (NUMPAD, Key4) => Numpad4,
+            (NUMPAD, Key5) => Numpad5,
+            (NUMPAD, Key6) => Numpad6,
+            (NUMPAD, Key7) => Numpad7,
+            (NUMPAD, Key8) => Numpad8,
+            (NUMPAD, Key9) => Numpad9,
+            (NUMPAD, Minus) => Subtract,
+            (NUMPAD, Slash) => Divide,
+            (NUMPAD, Period) => Decimal,
+            (NUMPAD, Comma) => NumpadComma,
+            (NUMPAD, Equals) => NumpadEquals,
+            (NUMPAD, Return) => NumpadEnter,
+            (_, key) => key,
+        }
+    }
 }
 
 /// Represents the current state of the keyboard modifiers
 ///
 /// Each field of this struct represents a modifier and is `true` if this modifier is active.
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ModifiersState {
     /// The "shift" key
     pub shift: bool,
@@ -493,7 +1114,59 @@ pub struct ModifiersState {
     pub logo: bool,
 }
 
-#[cfg(all(target_arch = "wasm32"))]
+/// A rectangle of the window in pixels, measured from its top-left corner like
+/// `CursorMoved.position`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Viewport {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Viewport {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Viewport {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The whole window, e.g. `Viewport::from_size(app.size())`.
+    pub fn from_size(size: (u32, u32)) -> Viewport {
+        Viewport::new(0.0, 0.0, size.0 as f64, size.1 as f64)
+    }
+
+    /// Converts a position in window pixels to normalized device coordinates: -1 to 1 across
+    /// the viewport, with y pointing up as in OpenGL.
+    pub fn to_ndc(&self, position: (f64, f64)) -> (f64, f64) {
+        let scale = |offset: f64, size: f64| if size > 0.0 { offset / size } else { 0.0 };
+        (
+            scale(position.0 - self.x, self.width) * 2.0 - 1.0,
+            1.0 - scale(position.1 - self.y, self.height) * 2.0,
+        )
+    }
+
+    /// Converts normalized device coordinates back to window pixels.
+    pub fn from_ndc(&self, ndc: (f64, f64)) -> (f64, f64) {
+        (
+            self.x + (ndc.0 + 1.0) / 2.0 * self.width,
+            self.y + (1.0 - ndc.1) / 2.0 * self.height,
+        )
+    }
+
+    pub fn contains(&self, position: (f64, f64)) -> bool {
+        position.0 >= self.x
+            && position.1 >= self.y
+            && position.0 < self.x + self.width
+            && position.1 < self.y + self.height
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
 impl MouseButton {
     pub fn from_mouse_button(btn: i16) -> MouseButton {
         match btn {
@@ -504,3 +1177,439 @@ impl MouseButton {
         }
     }
 }
+
+/// A button-like input tracked by `InputState`.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Input {
+    /// A key, identified by its semantic meaning.
+    Key(VirtualKeyCode),
+    /// A key, identified by its physical location.
+    ScanCode(ScanCode),
+    /// A mouse button.
+    Mouse(MouseButton),
+    /// A button of any connected gamepad. It is down while at least one pad holds it, pressed
+    /// when the first pad presses it and released when the last one lets go.
+    Gamepad(GamepadButton),
+}
+
+impl From<VirtualKeyCode> for Input {
+    fn from(key: VirtualKeyCode) -> Input {
+        Input::Key(key)
+    }
+}
+
+impl From<MouseButton> for Input {
+    fn from(button: MouseButton) -> Input {
+        Input::Mouse(button)
+    }
+}
+
+impl From<GamepadButton> for Input {
+    fn from(button: GamepadButton) -> Input {
+        Input::Gamepad(button)
+    }
+}
+
+/// Snapshot of the keyboard and mouse state, updated once per frame from the `Event` stream.
+///
+/// `App` keeps one of these up to date before every call to the frame callback, so "is this key
+/// held" and "was this button clicked this frame" can be asked directly instead of replaying the
+/// events by hand.
+#[derive(Default, Debug, Clone)]
+pub struct InputState {
+    down: HashSet<Input>,
+    pressed: HashSet<Input>,
+    released: HashSet<Input>,
+    cursor_position: Option<(f64, f64)>,
+    cursor_delta: (f64, f64),
+    mouse_motion: (f64, f64),
+    line_scroll_delta: (f32, f32),
+    pixel_scroll_delta: (f32, f32),
+    modifiers: ModifiersState,
+    /// Raw axes of devices other than gamepads, such as joysticks and tablets.
+    axes: HashMap<AxisId, f64>,
+    /// Buttons held on each pad, which `down` sums up as `Input::Gamepad`.
+    gamepad_buttons: HashSet<(DeviceId, GamepadButton)>,
+    gamepad_axes: HashMap<(DeviceId, AxisId), f64>,
+}
+
+impl InputState {
+    pub fn new() -> InputState {
+        InputState::default()
+    }
+
+    /// Starts a new frame and applies the frame's events.
+    ///
+    /// Everything that is reported "this frame" (presses, releases and deltas) is reset first.
+    /// Takes `Event`s as well as `TimedEvent`s.
+    pub fn update<I>(&mut self, events: I)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<Event>,
+    {
+        self.pressed.clear();
+        self.released.clear();
+        self.cursor_delta = (0.0, 0.0);
+        self.mouse_motion = (0.0, 0.0);
+        self.line_scroll_delta = (0.0, 0.0);
+        self.pixel_scroll_delta = (0.0, 0.0);
+
+        for event in events {
+            match *event.as_ref() {
+                Event::WindowEvent { ref event, .. } => self.handle_window_event(event),
+                Event::DeviceEvent {
+                    device_id,
+                    event: DeviceEvent::Motion { axis, value },
+                } => self.set_axis(device_id, axis, value),
+                Event::DeviceEvent {
+                    event: DeviceEvent::MouseMotion { delta },
+                    ..
+                } => {
+                    self.mouse_motion.0 += delta.0;
+                    self.mouse_motion.1 += delta.1;
+                }
+                // Other devices number their buttons too, e.g. raw mouse buttons, so only
+                // gamepad ids are gamepad buttons.
+                Event::DeviceEvent {
+                    device_id,
+                    event: DeviceEvent::Button { button, state },
+                } if device_id.kind() == DeviceKind::Gamepad => {
+                    if let Some(button) = GamepadButton::from_id(button) {
+                        self.set_gamepad_state(device_id, button, state);
+                    }
+                }
+                Event::DeviceEvent {
+                    device_id,
+                    event: DeviceEvent::Removed,
+                } if device_id.kind() == DeviceKind::Gamepad => self.remove_gamepad(device_id),
+                _ => (),
+            }
+        }
+    }
+
+    fn handle_window_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::KeyboardInput { ref input, .. } => {
+                self.modifiers = input.modifiers;
+                if let Some(key) = input.virtual_keycode {
+                    self.set_state(Input::Key(key), input.state);
+                }
+                if input.scancode != 0 {
+                    self.set_state(Input::ScanCode(input.scancode), input.state);
+                }
+            }
+            WindowEvent::MouseInput {
+                state,
+                button,
+                modifiers,
+                ..
+            } => {
+                self.modifiers = modifiers;
+                self.set_state(Input::Mouse(button), state);
+            }
+            WindowEvent::CursorMoved {
+                position,
+                modifiers,
+                ..
+            } => {
+                self.modifiers = modifiers;
+                if let Some(previous) = self.cursor_position {
+                    self.cursor_delta.0 += position.0 - previous.0;
+                    self.cursor_delta.1 += position.1 - previous.1;
+                }
+                self.cursor_position = Some(position);
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_position = None;
+            }
+            WindowEvent::MouseWheel {
+                delta, modifiers, ..
+            } => {
+                self.modifiers = modifiers;
+                match delta {
+                    MouseScrollDelta::LineDelta(x, y) => {
+                        self.line_scroll_delta.0 += x;
+                        self.line_scroll_delta.1 += y;
+                    }
+                    MouseScrollDelta::PixelDelta(x, y) => {
+                        self.pixel_scroll_delta.0 += x;
+                        self.pixel_scroll_delta.1 += y;
+                    }
+                }
+            }
+            WindowEvent::AxisMotion {
+                device_id,
+                axis,
+                value,
+            } => self.set_axis(device_id, axis, value),
+            WindowEvent::Focused(false) => {
+                // Releases are not delivered to an unfocused window, so treat everything as
+                // released rather than leaving keys stuck down. Gamepads are polled whatever
+                // has focus, so their releases still arrive.
+                let (gamepad, released): (HashSet<Input>, HashSet<Input>) = self
+                    .down
+                    .drain()
+                    .partition(|input| matches!(*input, Input::Gamepad(_)));
+                self.down = gamepad;
+                self.released.extend(released);
+                self.modifiers = ModifiersState::default();
+            }
+            _ => (),
+        }
+    }
+
+    fn set_state(&mut self, input: Input, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                // Key repeat sends further presses while the key is held.
+                if self.down.insert(input) {
+                    self.pressed.insert(input);
+                }
+            }
+            ElementState::Released => {
+                if self.down.remove(&input) {
+                    self.released.insert(input);
+                }
+            }
+        }
+    }
+
+    fn set_gamepad_state(&mut self, pad: DeviceId, button: GamepadButton, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                self.gamepad_buttons.insert((pad, button));
+            }
+            ElementState::Released => {
+                self.gamepad_buttons.remove(&(pad, button));
+                if self.gamepad_buttons.iter().any(|&(_, held)| held == button) {
+                    return;
+                }
+            }
+        }
+        self.set_state(Input::Gamepad(button), state);
+    }
+
+    /// Forgets a disconnected pad, releasing whatever only it held.
+    fn remove_gamepad(&mut self, pad: DeviceId) {
+        let held: Vec<GamepadButton> = self
+            .gamepad_buttons
+            .iter()
+            .filter(|&&(device_id, _)| device_id == pad)
+            .map(|&(_, button)| button)
+            .collect();
+        for button in held {
+            self.set_gamepad_state(pad, button, ElementState::Released);
+        }
+        self.gamepad_axes
+            .retain(|&(device_id, _), _| device_id != pad);
+    }
+
+    fn set_axis(&mut self, device_id: DeviceId, axis: AxisId, value: f64) {
+        if device_id.kind() == DeviceKind::Gamepad {
+            self.gamepad_axes.insert((device_id, axis), value);
+        } else {
+            self.axes.insert(axis, value);
+        }
+    }
+
+    /// Returns true while the input is held down.
+    pub fn is_down<I: Into<Input>>(&self, input: I) -> bool {
+        self.down.contains(&input.into())
+    }
+
+    /// Returns true if the input went down during this frame.
+    pub fn just_pressed<I: Into<Input>>(&self, input: I) -> bool {
+        self.pressed.contains(&input.into())
+    }
+
+    /// Returns true if the input went up during this frame.
+    pub fn just_released<I: Into<Input>>(&self, input: I) -> bool {
+        self.released.contains(&input.into())
+    }
+
+    /// The last known cursor position, or `None` if the cursor is outside the window.
+    pub fn cursor_position(&self) -> Option<(f64, f64)> {
+        self.cursor_position
+    }
+
+    /// How far the cursor moved during this frame, in pixels.
+    pub fn cursor_delta(&self) -> (f64, f64) {
+        self.cursor_delta
+    }
+
+    /// Raw mouse movement during this frame.
+    ///
+    /// Unlike `cursor_delta` this keeps reporting movement while the cursor is grabbed or at
+    /// the edge of the screen, which makes it the right choice for camera control.
+    pub fn mouse_motion(&self) -> (f64, f64) {
+        self.mouse_motion
+    }
+
+    /// Scroll amount of this frame for devices that scroll in lines.
+    pub fn scroll_delta(&self) -> (f32, f32) {
+        self.line_scroll_delta
+    }
+
+    /// Scroll amount of this frame for devices that scroll in pixels, such as touchpads.
+    pub fn pixel_scroll_delta(&self) -> (f32, f32) {
+        self.pixel_scroll_delta
+    }
+
+    /// The modifier keys active as of the last keyboard or mouse event.
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// The last reported value of a raw analog axis of a device other than a gamepad, such as
+    /// a joystick or tablet, or 0 if it never moved.
+    ///
+    /// Gamepad axes are kept apart and read with `gamepad_axis`.
+    pub fn axis(&self, axis: AxisId) -> f64 {
+        self.axes.get(&axis).cloned().unwrap_or(0.0)
+    }
+
+    /// The value of a gamepad axis on any pad: the one furthest from 0, or 0 if no pad moved
+    /// it.
+    pub fn gamepad_axis(&self, axis: GamepadAxis) -> f64 {
+        self.gamepad_axes
+            .iter()
+            .filter(|&(&(_, id), _)| id == axis.id())
+            .map(|(_, &value)| value)
+            .fold(0.0, |furthest, value| {
+                if value.abs() > furthest.abs() {
+                    value
+                } else {
+                    furthest
+                }
+            })
+    }
+
+    /// Returns true while one pad, e.g. `DeviceId::gamepad(0)`, holds a button.
+    pub fn is_pad_down(&self, pad: DeviceId, button: GamepadButton) -> bool {
+        self.gamepad_buttons.contains(&(pad, button))
+    }
+
+    /// The value of an axis of one pad, or 0 if it never moved or the pad is gone.
+    pub fn pad_axis(&self, pad: DeviceId, axis: GamepadAxis) -> f64 {
+        self.gamepad_axes
+            .get(&(pad, axis.id()))
+            .cloned()
+            .unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_EVENTS: &[Event] = &[];
+
+    fn window_event(event: WindowEvent) -> Event {
+        Event::WindowEvent {
+            window_id: WindowId::PRIMARY,
+            event,
+        }
+    }
+
+    fn key(key: VirtualKeyCode, state: ElementState) -> Event {
+        window_event(WindowEvent::KeyboardInput {
+            device_id: DeviceId::KEYBOARD,
+            input: KeyboardInput {
+                scancode: 0,
+                state,
+                virtual_keycode: Some(key),
+                modifiers: ModifiersState::default(),
+            },
+        })
+    }
+
+    fn cursor_moved(x: f64, y: f64) -> Event {
+        window_event(WindowEvent::CursorMoved {
+            device_id: DeviceId::MOUSE,
+            position: (x, y),
+            modifiers: ModifiersState::default(),
+            pointer: None,
+        })
+    }
+
+    #[test]
+    fn translates_dom_keys() {
+        // `KeyboardEvent.key`, `KeyboardEvent.location` and the expected keycode.
+        let cases = [
+            ("q", 0, Some(VirtualKeyCode::Q)),
+            ("Q", 0, Some(VirtualKeyCode::Q)),
+            ("Unidentified", 0, None),
+            ("5", 0, Some(VirtualKeyCode::Key5)),
+            ("5", 3, Some(VirtualKeyCode::Numpad5)),
+            ("Enter", 3, Some(VirtualKeyCode::NumpadEnter)),
+            ("Shift", 1, Some(VirtualKeyCode::LShift)),
+            ("Shift", 2, Some(VirtualKeyCode::RShift)),
+        ];
+        for &(key, location, expected) in cases.iter() {
+            let keycode =
+                VirtualKeyCode::from_key(key.to_string()).map(|key| key.with_location(location));
+            assert_eq!(keycode, expected, "{:?} at location {}", key, location);
+        }
+    }
+
+    #[test]
+    fn presses_last_one_frame() {
+        let mut input = InputState::new();
+        input.update(&[key(VirtualKeyCode::A, ElementState::Pressed)]);
+        assert!(input.just_pressed(VirtualKeyCode::A));
+        assert!(input.is_down(VirtualKeyCode::A));
+
+        input.update(NO_EVENTS);
+        assert!(!input.just_pressed(VirtualKeyCode::A));
+        assert!(input.is_down(VirtualKeyCode::A));
+
+        // Key repeat presses a held key again.
+        input.update(&[key(VirtualKeyCode::A, ElementState::Pressed)]);
+        assert!(!input.just_pressed(VirtualKeyCode::A));
+
+        input.update(&[key(VirtualKeyCode::A, ElementState::Released)]);
+        assert!(input.just_released(VirtualKeyCode::A));
+        assert!(!input.is_down(VirtualKeyCode::A));
+
+        input.update(NO_EVENTS);
+        assert!(!input.just_released(VirtualKeyCode::A));
+    }
+
+    #[test]
+    fn losing_focus_releases_everything() {
+        let mut input = InputState::new();
+        input.update(&[
+            key(VirtualKeyCode::A, ElementState::Pressed),
+            window_event(WindowEvent::MouseInput {
+                device_id: DeviceId::MOUSE,
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                modifiers: ModifiersState::default(),
+            }),
+        ]);
+        input.update(&[window_event(WindowEvent::Focused(false))]);
+        assert!(input.just_released(VirtualKeyCode::A));
+        assert!(input.just_released(MouseButton::Left));
+        assert!(!input.is_down(VirtualKeyCode::A));
+        assert!(!input.is_down(MouseButton::Left));
+    }
+
+    #[test]
+    fn cursor_delta_adds_up_within_a_frame() {
+        let mut input = InputState::new();
+        input.update(&[cursor_moved(10.0, 10.0)]);
+        assert_eq!(input.cursor_delta(), (0.0, 0.0));
+
+        input.update(&[
+            cursor_moved(12.0, 9.0),
+            cursor_moved(15.0, 5.0),
+            cursor_moved(16.0, 6.0),
+        ]);
+        assert_eq!(input.cursor_delta(), (6.0, -4.0));
+        assert_eq!(input.cursor_position(), Some((16.0, 6.0)));
+
+        input.update(NO_EVENTS);
+        assert_eq!(input.cursor_delta(), (0.0, 0.0));
+    }
+}
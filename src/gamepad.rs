@@ -0,0 +1,415 @@
+//! Gamepad polling with a standard button and axis layout.
+//!
+//! Both backends describe each connected pad as a `GamepadState` in the layout of the W3C
+//! "standard" gamepad. `Gamepads` compares consecutive snapshots and reports the differences as
+//! `DeviceEvent`s, so the same events come out of the browser Gamepad API, the native gamepad
+//! library and `FakeGamepads`.
+
+use std::cell::RefCell;
+use std::mem;
+use std::rc::Rc;
+
+use events::*;
+
+/// Buttons of the standard gamepad layout.
+///
+/// The discriminants are the `ButtonId`s reported in `DeviceEvent::Button`.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GamepadButton {
+    /// Bottom face button (A on Xbox, Cross on PlayStation).
+    South,
+    /// Right face button (B on Xbox, Circle on PlayStation).
+    East,
+    /// Left face button (X on Xbox, Square on PlayStation).
+    West,
+    /// Top face button (Y on Xbox, Triangle on PlayStation).
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    /// Pressing the left stick.
+    LeftStick,
+    /// Pressing the right stick.
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    /// The vendor button in the center (Xbox, PS, Home).
+    Mode,
+}
+
+/// Number of buttons in the standard layout.
+pub const BUTTON_COUNT: usize = 17;
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; BUTTON_COUNT] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftBumper,
+        GamepadButton::RightBumper,
+        GamepadButton::LeftTrigger,
+        GamepadButton::RightTrigger,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+        GamepadButton::Mode,
+    ];
+
+    pub fn id(self) -> ButtonId {
+        self as ButtonId
+    }
+
+    pub fn from_id(id: ButtonId) -> Option<GamepadButton> {
+        GamepadButton::ALL.get(id as usize).cloned()
+    }
+}
+
+/// Axes of the standard gamepad layout.
+///
+/// Sticks range from -1 to 1, with negative values to the left and up. Triggers range from 0
+/// (released) to 1 (fully pressed). The discriminants are the `AxisId`s reported in
+/// `DeviceEvent::Motion`.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// Number of axes in the standard layout.
+pub const AXIS_COUNT: usize = 6;
+
+impl GamepadAxis {
+    pub const ALL: [GamepadAxis; AXIS_COUNT] = [
+        GamepadAxis::LeftStickX,
+        GamepadAxis::LeftStickY,
+        GamepadAxis::RightStickX,
+        GamepadAxis::RightStickY,
+        GamepadAxis::LeftTrigger,
+        GamepadAxis::RightTrigger,
+    ];
+
+    pub fn id(self) -> AxisId {
+        self as AxisId
+    }
+
+    pub fn from_id(id: AxisId) -> Option<GamepadAxis> {
+        GamepadAxis::ALL.get(id as usize).cloned()
+    }
+}
+
+/// Snapshot of one connected gamepad.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GamepadState {
+    /// Name reported by the driver or browser.
+    pub name: String,
+    /// Pressed state of each button, indexed by `GamepadButton`.
+    pub buttons: [bool; BUTTON_COUNT],
+    /// Value of each axis, indexed by `GamepadAxis`.
+    pub axes: [f64; AXIS_COUNT],
+}
+
+impl GamepadState {
+    pub fn new<T: Into<String>>(name: T) -> GamepadState {
+        GamepadState {
+            name: name.into(),
+            ..GamepadState::default()
+        }
+    }
+
+    pub fn is_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons[button as usize]
+    }
+
+    pub fn value(&self, axis: GamepadAxis) -> f64 {
+        self.axes[axis as usize]
+    }
+}
+
+/// Where gamepad snapshots come from.
+pub trait GamepadSource {
+    /// Replaces `pads` with the current state of every gamepad slot. Slots of disconnected pads
+    /// are `None`.
+    fn poll(&mut self, pads: &mut Vec<Option<GamepadState>>);
+}
+
+/// A source without any gamepads.
+pub struct NoGamepads;
+
+impl GamepadSource for NoGamepads {
+    fn poll(&mut self, pads: &mut Vec<Option<GamepadState>>) {
+        pads.clear();
+    }
+}
+
+/// A source of virtual pads driven by code, for tests and input scripting.
+///
+/// Clones share the same pads, so keep one clone to drive the pads after handing another to
+/// `Gamepads::new`.
+#[derive(Clone, Default)]
+pub struct FakeGamepads {
+    pads: Rc<RefCell<Vec<Option<GamepadState>>>>,
+}
+
+impl FakeGamepads {
+    pub fn new() -> FakeGamepads {
+        FakeGamepads::default()
+    }
+
+    pub fn connect<T: Into<String>>(&self, index: usize, name: T) {
+        let mut pads = self.pads.borrow_mut();
+        if pads.len() <= index {
+            pads.resize(index + 1, None);
+        }
+        pads[index] = Some(GamepadState::new(name));
+    }
+
+    pub fn disconnect(&self, index: usize) {
+        if let Some(pad) = self.pads.borrow_mut().get_mut(index) {
+            *pad = None;
+        }
+    }
+
+    pub fn set_button(&self, index: usize, button: GamepadButton, pressed: bool) {
+        if let Some(&mut Some(ref mut pad)) = self.pads.borrow_mut().get_mut(index) {
+            pad.buttons[button as usize] = pressed;
+        }
+    }
+
+    pub fn set_axis(&self, index: usize, axis: GamepadAxis, value: f64) {
+        if let Some(&mut Some(ref mut pad)) = self.pads.borrow_mut().get_mut(index) {
+            pad.axes[axis as usize] = value;
+        }
+    }
+}
+
+impl GamepadSource for FakeGamepads {
+    fn poll(&mut self, pads: &mut Vec<Option<GamepadState>>) {
+        pads.clone_from(&self.pads.borrow());
+    }
+}
+
+/// Polls a `GamepadSource` once per frame and turns the changes into events.
+pub struct Gamepads {
+    source: Box<dyn GamepadSource>,
+    pads: Vec<Option<GamepadState>>,
+    current: Vec<Option<GamepadState>>,
+    /// Stick values closer to 0 than this are reported as 0.
+    pub deadzone: f64,
+}
+
+impl Gamepads {
+    pub fn new(source: Box<dyn GamepadSource>) -> Gamepads {
+        Gamepads {
+            source,
+            pads: Vec::new(),
+            current: Vec::new(),
+            deadzone: 0.1,
+        }
+    }
+
+    /// Replaces the source, e.g. with `FakeGamepads` in tests. Pads of the old source are
+    /// reported as removed on the next poll.
+    pub fn set_source(&mut self, source: Box<dyn GamepadSource>) {
+        self.source = source;
+    }
+
+    /// The state of the pad in a slot, as of the last poll.
+    pub fn get(&self, index: usize) -> Option<&GamepadState> {
+        self.pads.get(index).and_then(|pad| pad.as_ref())
+    }
+
+    /// Slot index and state of every connected pad.
+    pub fn connected(&self) -> impl Iterator<Item = (usize, &GamepadState)> {
+        self.pads
+            .iter()
+            .enumerate()
+            .filter_map(|(index, pad)| pad.as_ref().map(|pad| (index, pad)))
+    }
+
+    /// Polls the source and appends `Added`, `Removed`, `Button` and `Motion` device events
+    /// for everything that changed since the last poll. Their device id is
+    /// `DeviceId::gamepad(index)`.
+    pub fn poll(&mut self, events: &mut Vec<Event>) {
+        self.source.poll(&mut self.current);
+        for pad in self.current.iter_mut().flat_map(|pad| pad.as_mut()) {
+            // The first four axes are the sticks; triggers rest at 0 anyway.
+            for value in pad.axes[..4].iter_mut() {
+                if value.abs() < self.deadzone {
+                    *value = 0.0;
+                }
+            }
+        }
+
+        let slots = self.pads.len().max(self.current.len());
+        let idle = GamepadState::default();
+        for index in 0..slots {
+            let old = self.pads.get(index).and_then(|pad| pad.as_ref());
+            let new = self.current.get(index).and_then(|pad| pad.as_ref());
+            if old.is_none() && new.is_some() {
+                push_device_event(events, index, DeviceEvent::Added);
+            }
+            if old.is_none() && new.is_none() {
+                continue;
+            }
+            let removed = new.is_none();
+
+            // A removed pad releases its buttons and centers its axes first, so nothing stays
+            // stuck down in `InputState`.
+            let (old, new) = (old.unwrap_or(&idle), new.unwrap_or(&idle));
+            for button in GamepadButton::ALL.iter() {
+                let (was, is) = (old.is_pressed(*button), new.is_pressed(*button));
+                if was != is {
+                    let state = if is {
+                        ElementState::Pressed
+                    } else {
+                        ElementState::Released
+                    };
+                    push_device_event(
+                        events,
+                        index,
+                        DeviceEvent::Button {
+                            button: button.id(),
+                            state,
+                        },
+                    );
+                }
+            }
+            for axis in GamepadAxis::ALL.iter() {
+                let value = new.value(*axis);
+                if old.value(*axis) != value {
+                    push_device_event(
+                        events,
+                        index,
+                        DeviceEvent::Motion {
+                            axis: axis.id(),
+                            value,
+                        },
+                    );
+                }
+            }
+
+            if removed {
+                push_device_event(events, index, DeviceEvent::Removed);
+            }
+        }
+
+        mem::swap(&mut self.pads, &mut self.current);
+    }
+}
+
+fn push_device_event(events: &mut Vec<Event>, index: usize, event: DeviceEvent) {
+    events.push(Event::DeviceEvent {
+        device_id: DeviceId::gamepad(index),
+        event,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Polls once and feeds the events to `input` as one frame.
+    fn poll(gamepads: &mut Gamepads, input: &mut InputState) -> Vec<Event> {
+        let mut events = Vec::new();
+        gamepads.poll(&mut events);
+        input.update(&events);
+        events
+    }
+
+    #[test]
+    fn reports_changes_and_releases_removed_pads() {
+        let fake = FakeGamepads::new();
+        let mut gamepads = Gamepads::new(Box::new(fake.clone()));
+        let mut input = InputState::new();
+        let pad = DeviceId::gamepad(1);
+
+        fake.connect(1, "Pad");
+        let events = poll(&mut gamepads, &mut input);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            events[0],
+            Event::DeviceEvent {
+                device_id,
+                event: DeviceEvent::Added,
+            } if device_id == pad
+        ));
+        assert_eq!(gamepads.get(1).map(|pad| &pad.name[..]), Some("Pad"));
+
+        fake.set_button(1, GamepadButton::South, true);
+        fake.set_axis(1, GamepadAxis::LeftStickX, 0.05);
+        fake.set_axis(1, GamepadAxis::LeftStickY, -0.5);
+        let events = poll(&mut gamepads, &mut input);
+        // The stick's X value is inside the deadzone, so it stays at 0 and is not reported.
+        assert_eq!(events.len(), 2);
+        assert!(input.just_pressed(GamepadButton::South));
+        assert!(input.is_pad_down(pad, GamepadButton::South));
+        assert_eq!(input.gamepad_axis(GamepadAxis::LeftStickX), 0.0);
+        assert_eq!(input.pad_axis(pad, GamepadAxis::LeftStickY), -0.5);
+
+        assert!(poll(&mut gamepads, &mut input).is_empty());
+        assert!(input.is_down(GamepadButton::South));
+
+        fake.disconnect(1);
+        let events = poll(&mut gamepads, &mut input);
+        assert!(matches!(
+            events.last(),
+            Some(&Event::DeviceEvent {
+                device_id,
+                event: DeviceEvent::Removed,
+            }) if device_id == pad
+        ));
+        assert!(gamepads.get(1).is_none());
+        assert!(input.just_released(GamepadButton::South));
+        assert!(!input.is_pad_down(pad, GamepadButton::South));
+        assert_eq!(input.gamepad_axis(GamepadAxis::LeftStickY), 0.0);
+    }
+
+    #[test]
+    fn keeps_pads_apart() {
+        let fake = FakeGamepads::new();
+        let mut gamepads = Gamepads::new(Box::new(fake.clone()));
+        let mut input = InputState::new();
+
+        fake.connect(0, "First");
+        fake.connect(1, "Second");
+        fake.set_button(0, GamepadButton::Start, true);
+        fake.set_button(1, GamepadButton::Start, true);
+        fake.set_axis(0, GamepadAxis::RightStickX, 0.5);
+        fake.set_axis(1, GamepadAxis::RightStickX, -0.8);
+        poll(&mut gamepads, &mut input);
+        assert!(input.just_pressed(GamepadButton::Start));
+        assert_eq!(input.gamepad_axis(GamepadAxis::RightStickX), -0.8);
+        assert_eq!(
+            input.pad_axis(DeviceId::gamepad(0), GamepadAxis::RightStickX),
+            0.5
+        );
+
+        // The button stays down while the other pad holds it.
+        fake.set_button(0, GamepadButton::Start, false);
+        poll(&mut gamepads, &mut input);
+        assert!(input.is_down(GamepadButton::Start));
+        assert!(!input.just_released(GamepadButton::Start));
+
+        fake.set_button(1, GamepadButton::Start, false);
+        poll(&mut gamepads, &mut input);
+        assert!(input.just_released(GamepadButton::Start));
+    }
+}
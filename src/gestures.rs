@@ -0,0 +1,777 @@
+//! Tap, double-tap, long-press, pan, pinch and rotate recognition.
+//!
+//! `GestureRecognizer` reads the same per-frame events as `InputState`: touches, the left mouse
+//! button (optionally) and touchpad scrolling. It only needs the timed events and the frame
+//! time, so a `Recording` can drive it without a window.
+
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+use events::*;
+
+/// Thresholds of the recognizer. Distances are in pixels, times in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GestureConfig {
+    /// How far a finger may move and still tap or long-press.
+    pub tap_distance: f64,
+    /// Longest touch that still counts as a tap.
+    pub tap_time: f64,
+    /// Longest time from one tap to the next for a double-tap.
+    pub double_tap_time: f64,
+    /// Farthest distance between the two taps of a double-tap.
+    pub double_tap_distance: f64,
+    /// How long a finger has to stay down for a long-press.
+    pub long_press_time: f64,
+    /// How far a finger, or the center of two fingers, moves before a pan starts.
+    pub pan_distance: f64,
+    /// How much the distance between two fingers changes before a pinch starts.
+    pub pinch_distance: f64,
+    /// How far two fingers turn, in radians, before a rotation starts.
+    pub rotate_angle: f64,
+    /// Scale factor per pixel of touchpad pinch (scrolling with Ctrl held, as browsers report
+    /// it).
+    pub trackpad_zoom_speed: f64,
+    /// Time without touchpad scrolling after which a touchpad gesture ends.
+    pub trackpad_end_time: f64,
+    /// Smallest step of a mouse wheel that scrolls in pixels.
+    ///
+    /// Pixel scrolling along one axis in whole steps at least this large is taken for a mouse
+    /// wheel rather than a touchpad, and recognizes no gesture. Touchpads scroll in small steps,
+    /// usually along both axes.
+    pub wheel_step: f64,
+    /// Treat the left mouse button like a finger.
+    pub mouse: bool,
+}
+
+impl Default for GestureConfig {
+    fn default() -> GestureConfig {
+        GestureConfig {
+            tap_distance: 10.0,
+            tap_time: 300.0,
+            double_tap_time: 300.0,
+            double_tap_distance: 30.0,
+            long_press_time: 500.0,
+            pan_distance: 10.0,
+            pinch_distance: 10.0,
+            rotate_angle: 0.1,
+            trackpad_zoom_speed: 0.01,
+            trackpad_end_time: 150.0,
+            wheel_step: 50.0,
+            mouse: true,
+        }
+    }
+}
+
+/// A recognized gesture.
+///
+/// Continuous gestures start with `TouchPhase::Started`, continue with `Moved` and finish with
+/// `Ended`, or `Cancelled` when their touches were cancelled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Gesture {
+    /// A short touch without movement.
+    Tap { position: (f64, f64) },
+
+    /// A second tap close to the first one. The second tap is reported as `Tap` as well.
+    DoubleTap { position: (f64, f64) },
+
+    /// A touch held in place. No `Tap` follows when it is released.
+    LongPress { position: (f64, f64) },
+
+    /// One or two fingers, or touchpad scrolling, moving.
+    ///
+    /// `delta` is the movement since the last `Pan` event; for two fingers it is the movement
+    /// of their center.
+    Pan {
+        phase: TouchPhase,
+        position: (f64, f64),
+        delta: (f64, f64),
+        fingers: usize,
+    },
+
+    /// Two fingers, or touchpad pinching, moving apart or together.
+    ///
+    /// `scale` is the change since the last `Pinch` event, above 1 when zooming in.
+    Pinch {
+        phase: TouchPhase,
+        center: (f64, f64),
+        scale: f64,
+    },
+
+    /// Two fingers turning around their center.
+    ///
+    /// `angle` is the change since the last `Rotate` event in radians, clockwise on screen.
+    Rotate {
+        phase: TouchPhase,
+        center: (f64, f64),
+        angle: f64,
+    },
+}
+
+/// Finger id used for the left mouse button.
+const MOUSE_ID: u64 = u64::MAX;
+
+#[derive(Debug, Clone)]
+struct Pointer {
+    start: (f64, f64),
+    start_time: f64,
+    position: (f64, f64),
+    /// Moved farther than `tap_distance`, so it can no longer tap.
+    moved: bool,
+}
+
+/// Span, angle and center of the first two fingers, as of the last event of each gesture.
+#[derive(Debug, Clone, Copy)]
+struct TwoFingers {
+    span: f64,
+    angle: f64,
+    center: (f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TrackpadGesture {
+    Pan,
+    Pinch,
+}
+
+/// Turns events into `Gesture`s, frame by frame.
+#[derive(Debug, Clone, Default)]
+pub struct GestureRecognizer {
+    pub config: GestureConfig,
+    gestures: Vec<Gesture>,
+    time: f64,
+    pointers: BTreeMap<u64, Pointer>,
+    /// The window the fingers are down on. Touches on other windows wait until they are up.
+    window: Option<WindowId>,
+    /// More than one finger was down since all fingers were last up, so no tap, long-press or
+    /// one-finger pan until then.
+    multi: bool,
+    long_pressed: bool,
+    /// Position and time of the last tap, for double-taps.
+    last_tap: Option<((f64, f64), f64)>,
+    pan: Option<(f64, f64)>,
+    pan_fingers: usize,
+    /// Two-finger state when the fingers went down, and as of the last pinch and rotation.
+    two_fingers: Option<TwoFingers>,
+    pinch: Option<TwoFingers>,
+    rotate: Option<TwoFingers>,
+    cursor_position: Option<(f64, f64)>,
+    trackpad: Option<(TrackpadGesture, f64)>,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> GestureRecognizer {
+        GestureRecognizer {
+            config,
+            ..GestureRecognizer::default()
+        }
+    }
+
+    /// Starts a new frame at `time` (in milliseconds) and recognizes gestures in the frame's
+    /// events. Taps and double-taps are timed by the events' own times.
+    pub fn update<'a, I>(&mut self, time: f64, events: I)
+    where
+        I: IntoIterator<Item = &'a TimedEvent>,
+    {
+        self.gestures.clear();
+
+        for timed in events {
+            if let Event::WindowEvent {
+                window_id,
+                ref event,
+            } = timed.event
+            {
+                self.time = timed.time;
+                self.handle_window_event(window_id, event);
+            }
+        }
+
+        self.time = time;
+        self.check_long_press();
+        if let Some((_, last)) = self.trackpad {
+            if time - last >= self.config.trackpad_end_time {
+                self.end_trackpad();
+            }
+        }
+    }
+
+    /// The gestures recognized during this frame, in order.
+    pub fn gestures(&self) -> &[Gesture] {
+        &self.gestures
+    }
+
+    fn handle_window_event(&mut self, window_id: WindowId, event: &WindowEvent) {
+        let other_window = self.window.is_some_and(|window| window != window_id);
+        match *event {
+            WindowEvent::Touch(_)
+            | WindowEvent::CursorMoved { .. }
+            | WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+                if other_window =>
+            {
+                return;
+            }
+            _ => (),
+        }
+        match *event {
+            WindowEvent::Touch(touch) => match touch.phase {
+                TouchPhase::Started => self.pointer_down(window_id, touch.id, touch.location),
+                TouchPhase::Moved => self.pointer_moved(touch.id, touch.location),
+                TouchPhase::Ended => self.pointer_up(touch.id, touch.location, false),
+                TouchPhase::Cancelled => self.pointer_up(touch.id, touch.location, true),
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some(position);
+                if self.pointers.contains_key(&MOUSE_ID) {
+                    self.pointer_moved(MOUSE_ID, position);
+                }
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } if self.config.mouse => {
+                let position = self.cursor_position.unwrap_or((0.0, 0.0));
+                match state {
+                    ElementState::Pressed => self.pointer_down(window_id, MOUSE_ID, position),
+                    ElementState::Released => self.pointer_up(MOUSE_ID, position, false),
+                }
+            }
+            WindowEvent::MouseWheel {
+                delta: MouseScrollDelta::PixelDelta(x, y),
+                phase,
+                modifiers,
+                ..
+            } => {
+                let (x, y) = (f64::from(x), f64::from(y));
+                if self.is_trackpad(x, y, phase, modifiers.ctrl) {
+                    self.trackpad_scroll(x, y, modifiers.ctrl);
+                }
+            }
+            WindowEvent::Focused(false) => {
+                let ids: Vec<u64> = self.pointers.keys().cloned().collect();
+                for id in ids {
+                    let position = self.pointers[&id].position;
+                    self.pointer_up(id, position, true);
+                }
+                self.end_trackpad();
+            }
+            _ => (),
+        }
+    }
+
+    fn pointer_down(&mut self, window_id: WindowId, id: u64, position: (f64, f64)) {
+        self.window = Some(window_id);
+        self.pointers.insert(
+            id,
+            Pointer {
+                start: position,
+                start_time: self.time,
+                position,
+                moved: false,
+            },
+        );
+        if self.pointers.len() == 2 {
+            // A second finger turns a one-finger pan into a two-finger one.
+            self.multi = true;
+            self.end_pan(TouchPhase::Ended);
+            self.two_fingers = self.measure();
+        }
+    }
+
+    fn pointer_moved(&mut self, id: u64, position: (f64, f64)) {
+        let tap_distance = self.config.tap_distance;
+        match self.pointers.get_mut(&id) {
+            Some(pointer) => {
+                pointer.position = position;
+                if distance(pointer.start, position) > tap_distance {
+                    pointer.moved = true;
+                }
+            }
+            None => return,
+        }
+
+        if self.pointers.len() == 1 && !self.multi {
+            let start = self.pointers[&id].start;
+            self.pan_to(start, position, 1);
+        } else if self.pointers.len() >= 2 {
+            self.two_fingers_moved();
+        }
+    }
+
+    fn pointer_up(&mut self, id: u64, position: (f64, f64), cancelled: bool) {
+        let pointer = match self.pointers.remove(&id) {
+            Some(pointer) => pointer,
+            None => return,
+        };
+        let phase = if cancelled {
+            TouchPhase::Cancelled
+        } else {
+            TouchPhase::Ended
+        };
+
+        if self.pointers.len() < 2 {
+            self.end_two_fingers(phase);
+        }
+        if self.pan_fingers == 1 || self.pointers.len() < 2 {
+            self.end_pan(phase);
+        }
+
+        let tap = !cancelled
+            && !self.multi
+            && !self.long_pressed
+            && !pointer.moved
+            && distance(pointer.start, position) <= self.config.tap_distance
+            && self.time - pointer.start_time <= self.config.tap_time;
+        if tap {
+            self.gestures.push(Gesture::Tap { position });
+            let double = match self.last_tap {
+                Some((last, time)) => {
+                    self.time - time <= self.config.double_tap_time
+                        && distance(last, position) <= self.config.double_tap_distance
+                }
+                None => false,
+            };
+            if double {
+                self.gestures.push(Gesture::DoubleTap { position });
+                self.last_tap = None;
+            } else {
+                self.last_tap = Some((position, self.time));
+            }
+        }
+
+        if self.pointers.is_empty() {
+            self.window = None;
+            self.multi = false;
+            self.long_pressed = false;
+        }
+    }
+
+    fn check_long_press(&mut self) {
+        if self.multi || self.long_pressed || self.pointers.len() != 1 {
+            return;
+        }
+        let pointer = match self.pointers.values().next() {
+            Some(pointer) => pointer.clone(),
+            None => return,
+        };
+        if !pointer.moved && self.time - pointer.start_time >= self.config.long_press_time {
+            self.long_pressed = true;
+            self.last_tap = None;
+            self.gestures.push(Gesture::LongPress {
+                position: pointer.position,
+            });
+        }
+    }
+
+    /// Pans to `position`, starting the pan once it is `pan_distance` away from `start`.
+    fn pan_to(&mut self, start: (f64, f64), position: (f64, f64), fingers: usize) {
+        match self.pan {
+            Some(last) => {
+                self.pan = Some(position);
+                self.gestures.push(Gesture::Pan {
+                    phase: TouchPhase::Moved,
+                    position,
+                    delta: (position.0 - last.0, position.1 - last.1),
+                    fingers,
+                });
+            }
+            None if distance(start, position) > self.config.pan_distance => {
+                self.pan = Some(position);
+                self.pan_fingers = fingers;
+                self.gestures.push(Gesture::Pan {
+                    phase: TouchPhase::Started,
+                    position,
+                    delta: (position.0 - start.0, position.1 - start.1),
+                    fingers,
+                });
+            }
+            None => (),
+        }
+    }
+
+    fn end_pan(&mut self, phase: TouchPhase) {
+        if let Some(position) = self.pan.take() {
+            self.gestures.push(Gesture::Pan {
+                phase,
+                position,
+                delta: (0.0, 0.0),
+                fingers: self.pan_fingers,
+            });
+        }
+    }
+
+    fn two_fingers_moved(&mut self) {
+        let (start, now) = match (self.two_fingers, self.measure()) {
+            (Some(start), Some(now)) => (start, now),
+            _ => return,
+        };
+
+        self.pan_to(start.center, now.center, 2);
+
+        match self.pinch {
+            Some(last) => {
+                self.pinch = Some(now);
+                self.gestures.push(Gesture::Pinch {
+                    phase: TouchPhase::Moved,
+                    center: now.center,
+                    scale: ratio(now.span, last.span),
+                });
+            }
+            None if (now.span - start.span).abs() > self.config.pinch_distance => {
+                self.pinch = Some(now);
+                self.gestures.push(Gesture::Pinch {
+                    phase: TouchPhase::Started,
+                    center: now.center,
+                    scale: ratio(now.span, start.span),
+                });
+            }
+            None => (),
+        }
+
+        match self.rotate {
+            Some(last) => {
+                self.rotate = Some(now);
+                self.gestures.push(Gesture::Rotate {
+                    phase: TouchPhase::Moved,
+                    center: now.center,
+                    angle: angle_between(last.angle, now.angle),
+                });
+            }
+            None if angle_between(start.angle, now.angle).abs() > self.config.rotate_angle => {
+                self.rotate = Some(now);
+                self.gestures.push(Gesture::Rotate {
+                    phase: TouchPhase::Started,
+                    center: now.center,
+                    angle: angle_between(start.angle, now.angle),
+                });
+            }
+            None => (),
+        }
+    }
+
+    fn end_two_fingers(&mut self, phase: TouchPhase) {
+        self.two_fingers = None;
+        if let Some(last) = self.pinch.take() {
+            self.gestures.push(Gesture::Pinch {
+                phase,
+                center: last.center,
+                scale: 1.0,
+            });
+        }
+        if let Some(last) = self.rotate.take() {
+            self.gestures.push(Gesture::Rotate {
+                phase,
+                center: last.center,
+                angle: 0.0,
+            });
+        }
+    }
+
+    /// Span, angle and center of the two fingers with the lowest ids.
+    fn measure(&self) -> Option<TwoFingers> {
+        let mut pointers = self.pointers.values();
+        let (a, b) = match (pointers.next(), pointers.next()) {
+            (Some(a), Some(b)) => (a.position, b.position),
+            _ => return None,
+        };
+        Some(TwoFingers {
+            span: distance(a, b),
+            angle: (b.1 - a.1).atan2(b.0 - a.0),
+            center: ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0),
+        })
+    }
+
+    /// Whether pixel scrolling comes from a touchpad rather than a mouse wheel.
+    ///
+    /// Only some backends report the phases of touchpad scrolling; the others report every
+    /// scroll as `Moved`, so the size of the steps tells them apart. Once a touchpad gesture
+    /// started, scrolling continues it.
+    fn is_trackpad(&self, x: f64, y: f64, phase: TouchPhase, ctrl: bool) -> bool {
+        if ctrl || phase != TouchPhase::Moved || self.trackpad.is_some() {
+            return true;
+        }
+        let step = match (x, y) {
+            (x, 0.0) => x.abs(),
+            (0.0, y) => y.abs(),
+            _ => return true,
+        };
+        step < self.config.wheel_step || step.fract() != 0.0
+    }
+
+    /// Touchpads report two-finger scrolling as pixel scroll deltas, and browsers report
+    /// pinching as the same with Ctrl held.
+    fn trackpad_scroll(&mut self, x: f64, y: f64, ctrl: bool) {
+        let kind = if ctrl {
+            TrackpadGesture::Pinch
+        } else {
+            TrackpadGesture::Pan
+        };
+        let phase = match self.trackpad {
+            Some((active, _)) if active == kind => TouchPhase::Moved,
+            _ => {
+                self.end_trackpad();
+                TouchPhase::Started
+            }
+        };
+        self.trackpad = Some((kind, self.time));
+
+        let position = self.cursor_position.unwrap_or((0.0, 0.0));
+        self.gestures.push(match kind {
+            TrackpadGesture::Pan => Gesture::Pan {
+                phase,
+                position,
+                delta: (x, y),
+                fingers: 2,
+            },
+            TrackpadGesture::Pinch => Gesture::Pinch {
+                phase,
+                center: position,
+                scale: (y * self.config.trackpad_zoom_speed).exp(),
+            },
+        });
+    }
+
+    fn end_trackpad(&mut self) {
+        let kind = match self.trackpad.take() {
+            Some((kind, _)) => kind,
+            None => return,
+        };
+        let position = self.cursor_position.unwrap_or((0.0, 0.0));
+        self.gestures.push(match kind {
+            TrackpadGesture::Pan => Gesture::Pan {
+                phase: TouchPhase::Ended,
+                position,
+                delta: (0.0, 0.0),
+                fingers: 2,
+            },
+            TrackpadGesture::Pinch => Gesture::Pinch {
+                phase: TouchPhase::Ended,
+                center: position,
+                scale: 1.0,
+            },
+        });
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+fn ratio(span: f64, previous: f64) -> f64 {
+    if previous > 0.0 {
+        span / previous
+    } else {
+        1.0
+    }
+}
+
+/// The signed angle from `from` to `to`, in (-PI, PI].
+fn angle_between(from: f64, to: f64) -> f64 {
+    let mut angle = to - from;
+    while angle > PI {
+        angle -= 2.0 * PI;
+    }
+    while angle <= -PI {
+        angle += 2.0 * PI;
+    }
+    angle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: WindowId = WindowId(0);
+    const B: WindowId = WindowId(1);
+
+    fn touch(time: f64, window_id: WindowId, id: u64, phase: TouchPhase, x: f64) -> TimedEvent {
+        let touch = Touch {
+            device_id: DeviceId::TOUCH,
+            phase,
+            location: (x, 100.0),
+            id,
+            pointer: None,
+        };
+        TimedEvent::new(
+            time,
+            Event::WindowEvent {
+                window_id,
+                event: WindowEvent::Touch(touch),
+            },
+        )
+    }
+
+    fn scroll(time: f64, x: f32, y: f32) -> TimedEvent {
+        TimedEvent::new(
+            time,
+            Event::WindowEvent {
+                window_id: A,
+                event: WindowEvent::MouseWheel {
+                    device_id: DeviceId::MOUSE,
+                    delta: MouseScrollDelta::PixelDelta(x, y),
+                    phase: TouchPhase::Moved,
+                    modifiers: ModifiersState::default(),
+                },
+            },
+        )
+    }
+
+    /// Runs one frame per `(frame time, events)` and returns the gestures of every frame.
+    fn recognize(frames: Vec<(f64, Vec<TimedEvent>)>) -> Vec<Gesture> {
+        let mut recognizer = GestureRecognizer::default();
+        let mut gestures = Vec::new();
+        for (time, events) in frames {
+            recognizer.update(time, &events);
+            gestures.extend_from_slice(recognizer.gestures());
+        }
+        gestures
+    }
+
+    fn pinches(gestures: &[Gesture]) -> Vec<(TouchPhase, f64)> {
+        gestures
+            .iter()
+            .filter_map(|gesture| match *gesture {
+                Gesture::Pinch { phase, scale, .. } => Some((phase, scale)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tap_is_timed_by_its_events() {
+        // The release reaches the app in a frame long after it happened.
+        let gestures = recognize(vec![
+            (0.0, vec![touch(0.0, A, 1, TouchPhase::Started, 10.0)]),
+            (400.0, vec![touch(100.0, A, 1, TouchPhase::Ended, 12.0)]),
+        ]);
+        assert_eq!(
+            gestures,
+            vec![Gesture::Tap {
+                position: (12.0, 100.0)
+            }]
+        );
+    }
+
+    #[test]
+    fn long_press_replaces_the_tap() {
+        let gestures = recognize(vec![
+            (0.0, vec![touch(0.0, A, 1, TouchPhase::Started, 10.0)]),
+            (300.0, vec![]),
+            (600.0, vec![]),
+            (700.0, vec![touch(700.0, A, 1, TouchPhase::Ended, 10.0)]),
+        ]);
+        assert_eq!(
+            gestures,
+            vec![Gesture::LongPress {
+                position: (10.0, 100.0)
+            }]
+        );
+    }
+
+    #[test]
+    fn one_finger_pans() {
+        let gestures = recognize(vec![
+            (0.0, vec![touch(0.0, A, 1, TouchPhase::Started, 0.0)]),
+            (
+                16.0,
+                vec![
+                    touch(10.0, A, 1, TouchPhase::Moved, 5.0),
+                    touch(12.0, A, 1, TouchPhase::Moved, 20.0),
+                ],
+            ),
+            (32.0, vec![touch(30.0, A, 1, TouchPhase::Moved, 30.0)]),
+            (48.0, vec![touch(40.0, A, 1, TouchPhase::Ended, 30.0)]),
+        ]);
+        let pan = |phase, x, delta| Gesture::Pan {
+            phase,
+            position: (x, 100.0),
+            delta: (delta, 0.0),
+            fingers: 1,
+        };
+        assert_eq!(
+            gestures,
+            vec![
+                pan(TouchPhase::Started, 20.0, 20.0),
+                pan(TouchPhase::Moved, 30.0, 10.0),
+                pan(TouchPhase::Ended, 30.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn two_fingers_pinch() {
+        let gestures = recognize(vec![
+            (
+                0.0,
+                vec![
+                    touch(0.0, A, 1, TouchPhase::Started, 100.0),
+                    touch(5.0, A, 2, TouchPhase::Started, 200.0),
+                ],
+            ),
+            (16.0, vec![touch(10.0, A, 2, TouchPhase::Moved, 300.0)]),
+            (32.0, vec![touch(20.0, A, 2, TouchPhase::Moved, 500.0)]),
+            (48.0, vec![touch(30.0, A, 2, TouchPhase::Ended, 500.0)]),
+        ]);
+        assert_eq!(
+            pinches(&gestures),
+            vec![
+                (TouchPhase::Started, 2.0),
+                (TouchPhase::Moved, 2.0),
+                (TouchPhase::Ended, 1.0),
+            ]
+        );
+        assert!(!gestures
+            .iter()
+            .any(|gesture| matches!(*gesture, Gesture::Tap { .. })));
+    }
+
+    #[test]
+    fn touches_on_different_windows_do_not_pinch() {
+        let gestures = recognize(vec![
+            (
+                0.0,
+                vec![
+                    touch(0.0, A, 1, TouchPhase::Started, 100.0),
+                    touch(5.0, B, 2, TouchPhase::Started, 200.0),
+                ],
+            ),
+            (16.0, vec![touch(10.0, B, 2, TouchPhase::Moved, 300.0)]),
+            (32.0, vec![touch(20.0, A, 1, TouchPhase::Ended, 100.0)]),
+        ]);
+        assert_eq!(pinches(&gestures), vec![]);
+        assert_eq!(
+            gestures,
+            vec![Gesture::Tap {
+                position: (100.0, 100.0)
+            }]
+        );
+    }
+
+    #[test]
+    fn touchpad_scrolling_pans_but_a_mouse_wheel_does_not() {
+        let gestures = recognize(vec![(0.0, vec![scroll(0.0, 0.0, -100.0)])]);
+        assert_eq!(gestures, vec![]);
+
+        let gestures = recognize(vec![
+            (0.0, vec![scroll(0.0, -3.5, 2.25)]),
+            (16.0, vec![scroll(10.0, 0.0, -100.0)]),
+            (200.0, vec![]),
+        ]);
+        let pan = |phase, delta| Gesture::Pan {
+            phase,
+            position: (0.0, 0.0),
+            delta,
+            fingers: 2,
+        };
+        assert_eq!(
+            gestures,
+            vec![
+                pan(TouchPhase::Started, (-3.5, 2.25)),
+                pan(TouchPhase::Moved, (0.0, -100.0)),
+                pan(TouchPhase::Ended, (0.0, 0.0)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! Apps as structs with lifecycle hooks, as an alternative to a frame callback.
+
+use std::mem;
+
+use events::*;
+use {App, Frame};
+
+/// An app driven by `App::run_handler`.
+///
+/// Every hook has an empty default. Each frame, the backend calls `event` for every event of
+/// the frame, with `resized`, `suspended` and `resumed` called right before the `event` call
+/// they belong to. Then it calls `update` once, `fixed_update` for each of the frame's
+/// `Frame::steps`, and `render`.
+pub trait ApplicationHandler {
+    /// Called once, before the hooks of the first frame.
+    fn init(&mut self, _app: &mut App) {}
+
+    /// Called for every event of the frame. `app.events` is empty while this runs.
+    fn event(&mut self, _app: &mut App, _event: &TimedEvent) {}
+
+    /// A window changed size, in pixels.
+    fn resized(&mut self, _app: &mut App, _window_id: WindowId, _size: (u32, u32)) {}
+
+    /// The app went to the background, e.g. to a hidden tab on the web.
+    fn suspended(&mut self, _app: &mut App) {}
+
+    /// The app came back from the background.
+    fn resumed(&mut self, _app: &mut App) {}
+
+    /// Advances the app by `dt` milliseconds, the time since the previous frame.
+    fn update(&mut self, _app: &mut App, _dt: f64) {}
+
+    /// Advances the simulation by one fixed step of `dt` milliseconds, see `App::timestep`.
+    fn fixed_update(&mut self, _app: &mut App, _dt: f64) {}
+
+    /// Draws the frame. `alpha` is how far the frame is between the last and the next fixed
+    /// step, from 0 to 1, for interpolating between simulated states.
+    fn render(&mut self, _app: &mut App, _alpha: f64) {}
+
+    /// Called once when the app stops: on native after the last frame, on the web when the
+    /// page is unloaded.
+    fn exit(&mut self, _app: &mut App) {}
+}
+
+/// Calls the hooks of a handler from a frame callback.
+pub(crate) struct HandlerDriver<H> {
+    handler: H,
+    initialized: bool,
+}
+
+impl<H: ApplicationHandler> HandlerDriver<H> {
+    pub fn new(handler: H) -> HandlerDriver<H> {
+        HandlerDriver {
+            handler,
+            initialized: false,
+        }
+    }
+
+    pub fn frame(&mut self, app: &mut App, frame: &Frame) {
+        if !self.initialized {
+            self.initialized = true;
+            self.handler.init(app);
+        }
+
+        // The hooks get the app mutably, so the events are taken out of it meanwhile.
+        let events = mem::take(&mut app.events);
+        for event in &events {
+            match event.event {
+                Event::WindowEvent {
+                    window_id,
+                    event: WindowEvent::Resized(width, height),
+                } => self.handler.resized(app, window_id, (width, height)),
+                Event::Suspended(true) => self.handler.suspended(app),
+                Event::Suspended(false) => self.handler.resumed(app),
+                _ => (),
+            }
+            self.handler.event(app, event);
+        }
+        app.events = events;
+
+        self.handler.update(app, frame.delta);
+        for _ in 0..frame.steps {
+            self.handler.fixed_update(app, frame.step);
+        }
+        self.handler.render(app, frame.alpha);
+    }
+
+    pub fn exit(&mut self, app: &mut App) {
+        // An app that never ran a frame was never initialized either.
+        if self.initialized {
+            self.handler.exit(app);
+        }
+    }
+}
@@ -1,19 +1,57 @@
+#[cfg(all(not(target_arch = "wasm32"), feature = "gilrs"))]
+extern crate gilrs;
+#[cfg(not(target_arch = "wasm32"))]
+extern crate glutin;
+extern crate js_sys;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "json")]
+extern crate serde_json;
 extern crate wasm_bindgen;
 extern crate web_sys;
 
+pub mod bindings;
 pub mod events;
+pub mod gamepad;
+pub mod gestures;
+pub mod handler;
+#[cfg(target_arch = "wasm32")]
 mod render_loop;
-use render_loop::*;
+pub mod replay;
+pub mod stats;
+pub mod timing;
+use replay::RunMode;
+use std::error;
+use std::fmt;
+pub use timing::Frame;
 use wasm_bindgen::prelude::*;
 
-pub struct Callback<'r>(pub &'r FnMut(f64));
-
 //unsafe impl Send for App {}
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AppConfig {
     pub title: String,
     pub size: (u32, u32),
     pub vsync: bool,
+    /// Keeps mouse wheel and trackpad scrolling over the canvas from scrolling the page.
+    ///
+    /// Only used by the web backend.
+    pub capture_wheel: bool,
+    /// Stops the render loop while the page is hidden, e.g. in a background tab.
+    ///
+    /// Only used by the web backend.
+    pub pause_when_hidden: bool,
+    /// Resizes the canvas backing store to its CSS size times `devicePixelRatio`, reporting
+    /// `Resized` whenever that changes. `size` then sets the initial CSS size; restyle the
+    /// canvas (e.g. `width: 100%`) to make it follow the page layout.
+    ///
+    /// Only used by the web backend.
+    pub follow_css_size: bool,
+    /// Whether input is live, recorded or replayed from a recording.
+    pub run_mode: RunMode,
+    /// Simulation steps per second, see `Frame::steps`.
+    pub fixed_rate: f64,
 }
 
 impl AppConfig {
@@ -22,7 +60,49 @@ impl AppConfig {
             title: title.into(),
             size,
             vsync: true,
+            capture_wheel: true,
+            pause_when_hidden: true,
+            follow_css_size: false,
+            run_mode: RunMode::Live,
+            fixed_rate: 60.0,
+        }
+    }
+
+    /// Checks the settings `App::new` cannot work with.
+    pub(crate) fn validate(&self) -> Result<(), AppError> {
+        if !(self.fixed_rate > 0.0 && self.fixed_rate.is_finite()) {
+            return Err(AppError(format!(
+                "fixed_rate must be positive and finite, not {}",
+                self.fixed_rate
+            )));
         }
+        Ok(())
+    }
+}
+
+/// Why an `App` or one of its windows could not be created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppError(pub String);
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl error::Error for AppError {}
+
+#[cfg(target_arch = "wasm32")]
+impl From<JsValue> for AppError {
+    fn from(value: JsValue) -> AppError {
+        AppError(value.as_string().unwrap_or_else(|| format!("{:?}", value)))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl From<AppError> for JsValue {
+    fn from(error: AppError) -> JsValue {
+        JsValue::from_str(&error.0)
     }
 }
 
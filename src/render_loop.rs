@@ -6,38 +6,116 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 
 use app::App;
-use log;
+use Frame;
+
+/// The frame callback given to `App::run`.
+pub type FrameCallback = Box<dyn FnMut(&mut App, &Frame)>;
+
+/// Called once when the app stops.
+pub type ExitCallback = Box<dyn FnMut(&mut App)>;
 
 pub struct RenderLoop {
     app: Rc<RefCell<App>>,
     window: web_sys::Window,
-    pub callback: Option<Box<FnMut(&mut App)>>,
+    pub callback: Option<FrameCallback>,
+    /// Called once by `exit`.
+    pub exit: Option<ExitCallback>,
     animation_id: Option<i32>,
-    pub closure: Option<Closure<Fn(f64)>>,
+    pub closure: Option<Closure<dyn Fn(f64)>>,
+    /// Pause while the page is hidden and resume when it is shown again.
+    pub pause_when_hidden: bool,
+    paused_while_hidden: bool,
+    exited: bool,
 }
 
 impl RenderLoop {
     pub fn new(
         window: web_sys::Window,
         app: Rc<RefCell<App>>,
-        callback: Option<Box<FnMut(&mut App)>>,
+        callback: Option<FrameCallback>,
     ) -> RenderLoop {
         RenderLoop {
             app,
             window,
             callback,
+            exit: None,
             animation_id: None,
             closure: None,
+            pause_when_hidden: false,
+            paused_while_hidden: false,
+            exited: false,
         }
     }
 
-    pub fn render_loop(&mut self, _time: f64) {
-        if let Some(ref mut callback) = self.callback {
-            let app = &mut self.app.borrow_mut();
-            callback(app);
-            app._events.borrow_mut().clear();
+    /// Runs the frame of an animation frame request and requests the next one, unless the
+    /// callback paused or exited the loop.
+    pub fn render_loop(render_loop: &Rc<RefCell<RenderLoop>>, time: f64) {
+        RenderLoop::frame(render_loop, time, true);
+    }
+
+    /// Runs the callback once. With `schedule`, the next frame is requested afterwards.
+    ///
+    /// The loop is not borrowed while the callback runs, so the callback can pause, play or
+    /// exit it through `App::render_loop`.
+    fn frame(render_loop: &Rc<RefCell<RenderLoop>>, time: f64, schedule: bool) {
+        let (app, performance, mut callback, requested) = {
+            let mut render_loop = render_loop.borrow_mut();
+            let callback = match render_loop.callback.take() {
+                Some(callback) => callback,
+                None => return,
+            };
+            let performance = render_loop.window.performance();
+            (
+                render_loop.app.clone(),
+                performance,
+                callback,
+                render_loop.animation_id,
+            )
+        };
+        let more = {
+            let now = || performance.as_ref().map_or(0.0, |p| p.now());
+            let app = &mut match app.try_borrow_mut() {
+                Ok(app) => app,
+                Err(_) => {
+                    render_loop.borrow_mut().callback = Some(callback);
+                    return;
+                }
+            };
+            match app.begin_frame(time) {
+                Some(frame) => {
+                    let callback_start = now();
+                    callback(app, &frame);
+                    app.stats.callback_finished(&frame, now() - callback_start);
+                    true
+                }
+                None => false,
+            }
+        };
+
+        let mut render_loop = render_loop.borrow_mut();
+        if !more {
+            // The replay is over, which ends the app.
+            let _ = render_loop.pause();
+            render_loop.exited = true;
         }
+        if render_loop.exited {
+            // The app was borrowed until now, so the exit hook is still to be called.
+            let exit = render_loop.exit.take();
+            drop(render_loop);
+            if let Some(mut exit) = exit {
+                exit(&mut app.borrow_mut());
+            }
+            return;
+        }
+        render_loop.callback = Some(callback);
+        // Unless the callback paused the loop, or paused and played it again, which already
+        // requested a frame.
+        if schedule && render_loop.animation_id.is_some() && render_loop.animation_id == requested {
+            render_loop.request_frame();
+        }
+    }
 
+    fn request_frame(&mut self) {
         self.animation_id = if let Some(ref closure) = self.closure {
             Some(
                 self.window
@@ -49,12 +127,48 @@ impl RenderLoop {
         }
     }
 
+    fn now(&self) -> f64 {
+        self.window.performance().map_or(0.0, |p| p.now())
+    }
+
+    /// Called by the app when the page is hidden or shown again.
+    pub fn visibility_changed(
+        render_loop: &Rc<RefCell<RenderLoop>>,
+        hidden: bool,
+    ) -> Result<(), JsValue> {
+        let mut this = render_loop.borrow_mut();
+        if !this.pause_when_hidden {
+            return Ok(());
+        }
+        if hidden && !this.is_paused() {
+            this.pause()?;
+            this.paused_while_hidden = true;
+            // Run one more frame so the callback sees `Suspended(true)` right away instead of
+            // when the page comes back.
+            let now = this.now();
+            drop(this);
+            RenderLoop::frame(render_loop, now, false);
+        } else if !hidden && this.paused_while_hidden {
+            this.paused_while_hidden = false;
+            this.play()?;
+        }
+        Ok(())
+    }
+
     pub fn is_paused(&self) -> bool {
         self.animation_id.is_none()
     }
 
+    /// Resumes the loop from the next animation frame.
     pub fn play(&mut self) -> Result<(), JsValue> {
-        self.render_loop(0.0);
+        if self.is_paused() && !self.exited {
+            // Animation frame times share the clock of `performance.now()`.
+            let now = self.now();
+            if let Ok(mut app) = self.app.try_borrow_mut() {
+                app.resume_frames(now);
+            }
+            self.request_frame();
+        }
         Ok(())
     }
 
@@ -66,6 +180,20 @@ impl RenderLoop {
         Ok(())
     }
 
+    /// Stops the loop for good and calls the exit hook.
+    ///
+    /// Called from the frame callback, the hook runs once the callback returns.
+    pub fn exit(&mut self) {
+        let _ = self.pause();
+        self.callback = None;
+        self.exited = true;
+        if let Ok(mut app) = self.app.try_borrow_mut() {
+            if let Some(mut exit) = self.exit.take() {
+                exit(&mut app);
+            }
+        }
+    }
+
     pub fn play_pause(&mut self) -> Result<(), JsValue> {
         if self.is_paused() {
             self.play()?;
@@ -74,13 +202,4 @@ impl RenderLoop {
         }
         Ok(())
     }
-
-    pub fn run<F>(&mut self, mut callback: F)
-    where
-        F: 'static + FnMut(&mut App) -> (),
-    {
-        log("APP.RUN");
-        self.callback = Some(Box::new(callback));
-        self.play();
-    }
 }
@@ -0,0 +1,135 @@
+//! Recording of per-frame input and deterministic replay.
+//!
+//! With `RunMode::Record` the app stores the events of every frame together with the frame
+//! time. A `Recording` given to `RunMode::Replay` is then fed to the frame callback in place of
+//! live input, which makes bug reports reproducible and allows scripted playtests.
+
+#[cfg(all(feature = "json", not(target_arch = "wasm32")))]
+use std::fs::File;
+#[cfg(all(feature = "json", not(target_arch = "wasm32")))]
+use std::io;
+#[cfg(all(feature = "json", not(target_arch = "wasm32")))]
+use std::path::Path;
+use std::path::PathBuf;
+use std::vec;
+
+#[cfg(feature = "json")]
+use serde_json;
+
+use events::TimedEvent;
+
+/// The events of one frame and the time the frame started, in milliseconds.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RecordedFrame {
+    pub time: f64,
+    pub events: Vec<TimedEvent>,
+}
+
+/// A sequence of recorded frames.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Recording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn new() -> Recording {
+        Recording::default()
+    }
+
+    pub fn push(&mut self, time: f64, events: &[TimedEvent]) {
+        self.frames.push(RecordedFrame {
+            time,
+            events: events.to_vec(),
+        });
+    }
+
+    /// Duration between the first and the last frame, in milliseconds.
+    pub fn duration(&self) -> f64 {
+        match (self.frames.first(), self.frames.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Recording, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[cfg(all(feature = "json", not(target_arch = "wasm32")))]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(io::BufWriter::new(file), self).map_err(io::Error::from)
+    }
+
+    #[cfg(all(feature = "json", not(target_arch = "wasm32")))]
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Recording> {
+        let file = File::open(path)?;
+        serde_json::from_reader(io::BufReader::new(file)).map_err(io::Error::from)
+    }
+}
+
+/// How `App::run` gets its input.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RunMode {
+    /// Live input.
+    #[default]
+    Live,
+
+    /// Live input, recorded frame by frame.
+    ///
+    /// The recording so far is available from `App::recording`. On native targets built with
+    /// the `json` feature it is also written to `path` when the app exits.
+    Record { path: Option<PathBuf> },
+
+    /// Input is taken from a recording instead of the devices, and the app stops when the
+    /// recording ends.
+    Replay {
+        recording: Recording,
+        /// Wait for the recorded frame times instead of replaying as fast as possible.
+        ///
+        /// The web backend always replays one recorded frame per animation frame.
+        realtime: bool,
+        /// Run without creating a window. Drawing is not possible in this mode.
+        ///
+        /// Only supported by the native backend.
+        headless: bool,
+    },
+}
+
+/// Hands out the frames of a recording one by one.
+pub(crate) struct Player {
+    frames: vec::IntoIter<RecordedFrame>,
+    /// Time of the first frame, so replays start at 0 like live runs.
+    start: Option<f64>,
+}
+
+impl Player {
+    pub fn new(recording: Recording) -> Player {
+        let start = recording.frames.first().map(|frame| frame.time);
+        Player {
+            frames: recording.frames.into_iter(),
+            start,
+        }
+    }
+
+    /// The next frame with its times relative to the start of the recording.
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        let start = self.start.unwrap_or(0.0);
+        self.frames.next().map(|mut frame| {
+            frame.time -= start;
+            for event in &mut frame.events {
+                event.time -= start;
+            }
+            frame
+        })
+    }
+}
@@ -0,0 +1,282 @@
+//! Rolling frame statistics.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use timing::Frame;
+
+/// A frame that took longer than `FrameStats::budget`, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlowFrame {
+    /// `Frame::number` of the slow frame.
+    pub number: u64,
+    /// Time from the start of the frame to the start of the next one.
+    pub duration: f64,
+    /// Time spent in the frame callback.
+    pub callback_time: f64,
+}
+
+type SlowFrameCallback = Box<dyn FnMut(&SlowFrame)>;
+
+/// Frame rate and frame time statistics over the last `capacity` frames, kept by `App::stats`.
+///
+/// Times are in milliseconds. A frame is counted once the next one starts, since only then is
+/// its duration known.
+pub struct FrameStats {
+    capacity: usize,
+    /// Duration and callback time of each kept frame, oldest first.
+    frames: VecDeque<(f64, f64)>,
+    /// Callback time of the frame that is still running.
+    pending: Option<(u64, f64)>,
+    dropped: u64,
+    /// Expected time between two display refreshes.
+    ///
+    /// Browsers do not report the refresh rate, so it defaults to 60 Hz.
+    pub refresh_interval: f64,
+    /// Frames longer than this are passed to the slow frame callback.
+    pub budget: Option<f64>,
+    on_slow_frame: Option<SlowFrameCallback>,
+}
+
+impl FrameStats {
+    pub fn new(capacity: usize) -> FrameStats {
+        FrameStats {
+            capacity,
+            frames: VecDeque::with_capacity(capacity),
+            pending: None,
+            dropped: 0,
+            refresh_interval: 1000.0 / 60.0,
+            budget: None,
+            on_slow_frame: None,
+        }
+    }
+
+    /// Calls `callback` for every frame longer than `budget` milliseconds.
+    pub fn set_slow_frame_callback<F>(&mut self, budget: f64, callback: F)
+    where
+        F: 'static + FnMut(&SlowFrame),
+    {
+        self.budget = Some(budget);
+        self.on_slow_frame = Some(Box::new(callback));
+    }
+
+    /// Counts the previous frame, now that `frame` started. Called by the backends.
+    pub(crate) fn frame_started(&mut self, frame: &Frame) {
+        let (number, callback_time) = match self.pending.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+        // Replays can repeat frame times; such frames say nothing about the frame rate.
+        if frame.delta <= 0.0 {
+            return;
+        }
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((frame.delta, callback_time));
+
+        // A frame that spans several refreshes missed all but the last one.
+        let refreshes = (frame.delta / self.refresh_interval).round() as u64;
+        self.dropped += refreshes.saturating_sub(1);
+
+        if let (Some(budget), Some(ref mut on_slow_frame)) = (self.budget, &mut self.on_slow_frame)
+        {
+            if frame.delta > budget {
+                on_slow_frame(&SlowFrame {
+                    number,
+                    duration: frame.delta,
+                    callback_time,
+                });
+            }
+        }
+    }
+
+    /// Forgets the frame that was running when the render loop paused. Called by the web
+    /// backend when the loop resumes; native loops never pause.
+    ///
+    /// That frame lasted until the pause, and the time after it is not a frame, so it is not
+    /// counted at all rather than as one slow frame and many dropped ones.
+    #[cfg(any(target_arch = "wasm32", test))]
+    pub(crate) fn resumed(&mut self) {
+        self.pending = None;
+    }
+
+    /// Records how long the callback of `frame` took. Called by the backends.
+    pub(crate) fn callback_finished(&mut self, frame: &Frame, callback_time: f64) {
+        self.pending = Some((frame.number, callback_time));
+    }
+
+    /// Number of frames the statistics are taken over, at most `capacity`.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Frames per second of the last frame.
+    pub fn fps(&self) -> f64 {
+        self.frames
+            .back()
+            .map_or(0.0, |&(duration, _)| 1000.0 / duration)
+    }
+
+    pub fn average_fps(&self) -> f64 {
+        match self.average_frame_time() {
+            time if time > 0.0 => 1000.0 / time,
+            _ => 0.0,
+        }
+    }
+
+    /// Frames per second of the longest frame.
+    pub fn min_fps(&self) -> f64 {
+        let longest = self.durations().fold(0.0, f64::max);
+        if longest > 0.0 {
+            1000.0 / longest
+        } else {
+            0.0
+        }
+    }
+
+    /// Frames per second of the shortest frame.
+    pub fn max_fps(&self) -> f64 {
+        let shortest = self.durations().fold(f64::INFINITY, f64::min);
+        if shortest.is_finite() {
+            1000.0 / shortest
+        } else {
+            0.0
+        }
+    }
+
+    pub fn average_frame_time(&self) -> f64 {
+        average(self.durations(), self.len())
+    }
+
+    /// The frame time that `percentile` percent of the frames do not exceed, e.g. 99 for the
+    /// 99th percentile.
+    pub fn frame_time_percentile(&self, percentile: f64) -> f64 {
+        let mut durations: Vec<f64> = self.durations().collect();
+        if durations.is_empty() {
+            return 0.0;
+        }
+        durations.sort_by(f64::total_cmp);
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * durations.len() as f64).ceil();
+        durations[(rank as usize).max(1) - 1]
+    }
+
+    /// Counts of frame times in buckets `bucket` milliseconds wide, starting at 0.
+    pub fn histogram(&self, bucket: f64) -> Vec<usize> {
+        let mut counts = Vec::new();
+        for duration in self.durations() {
+            let index = (duration / bucket) as usize;
+            if counts.len() <= index {
+                counts.resize(index + 1, 0);
+            }
+            counts[index] += 1;
+        }
+        counts
+    }
+
+    /// Display refreshes missed since the app started, going by `refresh_interval`.
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Average time spent in the frame callback.
+    pub fn average_callback_time(&self) -> f64 {
+        average(
+            self.frames.iter().map(|&(_, callback)| callback),
+            self.len(),
+        )
+    }
+
+    /// Average time per frame spent outside the frame callback: in the backend, the browser
+    /// or waiting for the display.
+    pub fn average_outside_time(&self) -> f64 {
+        self.average_frame_time() - self.average_callback_time()
+    }
+
+    fn durations<'a>(&'a self) -> impl Iterator<Item = f64> + 'a {
+        self.frames.iter().map(|&(duration, _)| duration)
+    }
+}
+
+impl Default for FrameStats {
+    /// Statistics over the last 120 frames.
+    fn default() -> FrameStats {
+        FrameStats::new(120)
+    }
+}
+
+impl fmt::Debug for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FrameStats")
+            .field("frames", &self.frames.len())
+            .field("average_fps", &self.average_fps())
+            .field("dropped", &self.dropped)
+            .field("budget", &self.budget)
+            .finish()
+    }
+}
+
+fn average<I: Iterator<Item = f64>>(values: I, len: usize) -> f64 {
+    if len == 0 {
+        0.0
+    } else {
+        values.sum::<f64>() / len as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use timing::FixedTimestep;
+
+    /// Runs frames starting at `times` through `stats`, each with a 1 ms callback.
+    fn run(stats: &mut FrameStats, previous: &mut Option<Frame>, times: &[f64]) {
+        let mut timestep = FixedTimestep::default();
+        for &time in times {
+            let frame = Frame::after(*previous, time, &mut timestep);
+            stats.frame_started(&frame);
+            stats.callback_finished(&frame, 1.0);
+            *previous = Some(frame);
+        }
+    }
+
+    #[test]
+    fn counts_frames_once_they_end() {
+        let mut stats = FrameStats::new(4);
+        let mut previous = None;
+        run(&mut stats, &mut previous, &[0.0, 16.0, 32.0, 48.0]);
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.average_frame_time(), 16.0);
+        assert_eq!(stats.average_callback_time(), 1.0);
+        assert_eq!(stats.dropped_frames(), 0);
+
+        run(&mut stats, &mut previous, &[98.0, 114.0]);
+        assert_eq!(stats.len(), 4);
+        assert_eq!(stats.dropped_frames(), 2);
+        assert_eq!(stats.frame_time_percentile(100.0), 50.0);
+    }
+
+    #[test]
+    fn skips_the_frame_before_a_pause() {
+        let slow = Rc::new(Cell::new(0));
+        let mut stats = FrameStats::new(10);
+        let slow1 = slow.clone();
+        stats.set_slow_frame_callback(20.0, move |_| slow1.set(slow1.get() + 1));
+
+        let mut previous = None;
+        run(&mut stats, &mut previous, &[0.0, 16.0, 32.0]);
+        stats.resumed();
+        // The page was hidden for ten seconds after the frame at 32 ms.
+        run(&mut stats, &mut previous, &[10_032.0, 10_048.0]);
+
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.dropped_frames(), 0);
+        assert_eq!(slow.get(), 0);
+    }
+}
@@ -0,0 +1,164 @@
+//! Frame timing and fixed-rate simulation steps.
+//!
+//! Frames come at whatever rate the display and the app manage. Simulations that must not
+//! depend on it run in fixed steps instead: every frame adds its duration to an accumulator and
+//! takes as many whole steps out of it as fit. What is left over gives `Frame::alpha`, for
+//! drawing between the last two simulated states.
+
+/// Timing of the frame passed to the `App::run` callback, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Frame {
+    /// Counts frames from 0.
+    pub number: u64,
+    /// Start of the frame, the same as `App::frame_time`.
+    pub time: f64,
+    /// Time since the start of the previous frame, or 0 for the first frame.
+    ///
+    /// Time the web render loop spends paused, e.g. in a hidden tab, is left out.
+    pub delta: f64,
+    /// Time since the start of the first frame.
+    pub total: f64,
+    /// Number of fixed simulation steps to run this frame.
+    pub steps: u32,
+    /// Length of one simulation step, from `App::timestep`.
+    pub step: f64,
+    /// How far the frame is from the last simulation step towards the next one, from 0 to 1.
+    pub alpha: f64,
+}
+
+impl Frame {
+    /// The frame that starts at `time` and follows `previous`.
+    pub(crate) fn after(previous: Option<Frame>, time: f64, timestep: &mut FixedTimestep) -> Frame {
+        let (number, delta, total) = match previous {
+            Some(previous) => {
+                let delta = time - previous.time;
+                (previous.number + 1, delta, previous.total + delta)
+            }
+            None => (0, 0.0, 0.0),
+        };
+        let steps = timestep.advance(delta);
+        Frame {
+            number,
+            time,
+            delta,
+            total,
+            steps,
+            step: timestep.step,
+            alpha: timestep.alpha(),
+        }
+    }
+}
+
+/// Accumulates frame time into fixed-length simulation steps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedTimestep {
+    /// Length of one step, in milliseconds.
+    pub step: f64,
+    /// Most steps taken in one frame.
+    ///
+    /// If simulating is slower than real time, every frame would need more steps than the last
+    /// (the "spiral of death"). Time beyond this many steps is dropped instead, so the
+    /// simulation slows down rather than the frame rate.
+    pub max_steps: u32,
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    /// Steps at `rate` steps per second.
+    ///
+    /// Panics unless `rate` is positive and finite.
+    pub fn new(rate: f64) -> FixedTimestep {
+        assert!(
+            rate > 0.0 && rate.is_finite(),
+            "fixed timestep rate must be positive and finite, not {}",
+            rate
+        );
+        FixedTimestep {
+            step: 1000.0 / rate,
+            max_steps: 5,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Adds `delta` milliseconds and returns how many steps are due.
+    pub fn advance(&mut self, delta: f64) -> u32 {
+        self.accumulator += delta.max(0.0);
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        if self.accumulator >= self.step {
+            self.accumulator %= self.step;
+        }
+        steps
+    }
+
+    /// Time since the last step as a fraction of a step.
+    pub fn alpha(&self) -> f64 {
+        self.accumulator / self.step
+    }
+
+    /// Drops the accumulated time, e.g. after the app was paused.
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
+
+impl Default for FixedTimestep {
+    /// 60 steps per second.
+    fn default() -> FixedTimestep {
+        FixedTimestep::new(60.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_whole_steps_and_keeps_the_rest() {
+        let mut timestep = FixedTimestep::new(100.0);
+        assert_eq!(timestep.advance(25.0), 2);
+        assert_eq!(timestep.alpha(), 0.5);
+        assert_eq!(timestep.advance(5.0), 1);
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.advance(-5.0), 0);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn drops_time_beyond_max_steps() {
+        let mut timestep = FixedTimestep::new(100.0);
+        // A one second hitch: only five steps are taken and the remainder is less than a step.
+        assert_eq!(timestep.advance(1003.0), 5);
+        assert_eq!(timestep.alpha(), 0.3);
+        assert_eq!(timestep.advance(7.0), 1);
+
+        for delta in [0.0, 3.0, 9.99, 10.0, 55.5, 1e6].iter() {
+            timestep.advance(*delta);
+            let alpha = timestep.alpha();
+            assert!(
+                (0.0..1.0).contains(&alpha),
+                "alpha {} after {}",
+                alpha,
+                delta
+            );
+        }
+    }
+
+    #[test]
+    fn reset_drops_the_accumulated_time() {
+        let mut timestep = FixedTimestep::new(100.0);
+        timestep.advance(8.0);
+        timestep.reset();
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.advance(8.0), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_a_zero_rate() {
+        FixedTimestep::new(0.0);
+    }
+}